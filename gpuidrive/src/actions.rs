@@ -0,0 +1,51 @@
+//! Every command the user can bind a key to.
+//!
+//! These are referred to as `gpuidrive::<Name>` in the user's keymap.
+
+use gpui::actions;
+
+actions!(
+    gpuidrive,
+    [
+        QuitApp,
+        CloseWindow,
+        // Navigation
+        GoBack,
+        GoForward,
+        GoUp,
+        OpenSelected,
+        OpenSelectedExternally,
+        // Selection
+        SelectPrevious,
+        SelectNext,
+        SelectPageUp,
+        SelectPageDown,
+        SelectFirst,
+        SelectLast,
+        ClearSelection,
+        // Files
+        NewFolder,
+        Rename,
+        CopyFiles,
+        CutFiles,
+        PasteFiles,
+        MoveToTrash,
+        Compress,
+        ExtractHere,
+        ExtractToFolder,
+        // Views
+        ToggleQuickPreview,
+        ToggleProperties,
+        ToggleHiddenFiles,
+        OpenFilter,
+        SearchSubfolders,
+        SearchContents,
+        FindFile,
+        JumpToFolder,
+        ToggleCommandPalette,
+        // Pickers
+        Confirm,
+        SecondaryConfirm,
+        Dismiss,
+    ]
+);
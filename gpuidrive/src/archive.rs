@@ -0,0 +1,832 @@
+//! Zip and tar archives: browsing their entries like directories, creating and extracting them.
+//!
+//! Everything is pure Rust so it works without `zip`, `tar` or `xz` being installed.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Seek, Write},
+    os::unix::fs::PermissionsExt,
+    path::{Component, Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeZone, Timelike};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use lzma_rust2::{XzOptions, XzReader, XzWriter};
+use ruzstd::{
+    decoding::StreamingDecoder,
+    encoding::{CompressionLevel, FrameCompressor},
+};
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarZst,
+}
+
+/// The file name suffixes of each format.
+const EXTENSIONS: &[(&str, Format)] = &[
+    (".zip", Format::Zip),
+    (".tar", Format::Tar),
+    (".tar.gz", Format::TarGz),
+    (".tgz", Format::TarGz),
+    (".tar.xz", Format::TarXz),
+    (".txz", Format::TarXz),
+    (".tar.zst", Format::TarZst),
+    (".tzst", Format::TarZst),
+];
+
+/// The format of the archive at `path` and the length of its extension.
+fn extension(path: &Path) -> Option<(Format, usize)> {
+    let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+    EXTENSIONS
+        .iter()
+        .find(|(extension, _)| name.ends_with(extension))
+        .map(|(extension, format)| (*format, extension.len()))
+}
+
+/// The file name of an archive without its extension, eg. `foo` for `foo.tar.gz`.
+pub fn stem(path: &Path) -> Option<String> {
+    let (_, len) = extension(path)?;
+    // TODO: Handle non-utf8 file names
+    let name = path.file_name()?.to_string_lossy();
+    Some(name[..name.len() - len].to_string()).filter(|stem| !stem.is_empty())
+}
+
+impl Format {
+    /// By the file name, eg. `foo.tar.gz`.
+    pub fn detect(path: &Path) -> Option<Self> {
+        extension(path).map(|(format, _)| format)
+    }
+
+    /// Including the leading `.`, eg. `.tar.gz`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Zip => ".zip",
+            Self::Tar => ".tar",
+            Self::TarGz => ".tar.gz",
+            Self::TarXz => ".tar.xz",
+            Self::TarZst => ".tar.zst",
+        }
+    }
+
+    /// The next format to create, for cycling through them in the compress dialog.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Zip => Self::TarGz,
+            Self::TarGz => Self::TarXz,
+            Self::TarXz => Self::TarZst,
+            Self::TarZst => Self::Tar,
+            Self::Tar => Self::Zip,
+        }
+    }
+
+    /// Whether the compression level makes a difference. tar isn't compressed and ruzstd only
+    /// implements its fastest level.
+    pub fn has_levels(self) -> bool {
+        !matches!(self, Self::Tar | Self::TarZst)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Zip => "ZIP archive",
+            Self::Tar => "tar archive",
+            Self::TarGz => "gzip compressed tar archive",
+            Self::TarXz => "xz compressed tar archive",
+            Self::TarZst => "Zstandard compressed tar archive",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Level {
+    Fast,
+    #[default]
+    Normal,
+    Best,
+}
+
+impl Level {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Fast => "Fast",
+            Self::Normal => "Normal",
+            Self::Best => "Best",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Fast => Self::Normal,
+            Self::Normal => Self::Best,
+            Self::Best => Self::Fast,
+        }
+    }
+
+    /// The level for deflate and gzip, which is also xz's preset.
+    fn number(self) -> u32 {
+        match self {
+            Self::Fast => 1,
+            Self::Normal => 6,
+            Self::Best => 9,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// Relative to the root of the archive.
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+    /// Only zip compresses each entry separately.
+    pub compressed_size: Option<u64>,
+    pub modified: Option<DateTime<Local>>,
+    /// Unix permissions, if the archive stores them.
+    pub mode: Option<u32>,
+}
+
+/// Split a path inside an archive, eg. `/a/foo.zip/dir`, into the archive and the path within it.
+pub fn split(path: &Path) -> Option<(&Path, &Path)> {
+    let archive = path
+        .ancestors()
+        .find(|ancestor| Format::detect(ancestor).is_some() && ancestor.is_file())?;
+    Some((archive, path.strip_prefix(archive).ok()?))
+}
+
+/// Only keep the normal components of a path from an archive, so it can't escape the directory
+/// it's extracted to. Returns `None` if the path tries to with `..`.
+pub fn sanitize(path: &Path) -> Option<PathBuf> {
+    let mut sanitized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => sanitized.push(part),
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+            Component::ParentDir => return None,
+        }
+    }
+    (!sanitized.as_os_str().is_empty()).then_some(sanitized)
+}
+
+/// Every entry in the archive at `path`. For compressed tar archives this has to decompress the
+/// whole archive so can be slow.
+pub fn list(path: &Path) -> io::Result<Vec<Entry>> {
+    let format = Format::detect(path)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not an archive"))?;
+    let file = BufReader::new(File::open(path)?);
+    match format {
+        Format::Zip => list_zip(file),
+        Format::Tar => list_tar(file),
+        Format::TarGz => list_tar(GzDecoder::new(file)),
+        Format::TarXz => list_tar(XzReader::new(file, true)),
+        Format::TarZst => list_tar(StreamingDecoder::new(file).map_err(io::Error::other)?),
+    }
+}
+
+fn list_zip(file: impl Read + Seek) -> io::Result<Vec<Entry>> {
+    let mut archive = ZipArchive::new(file)?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for ix in 0..archive.len() {
+        let file = archive.by_index_raw(ix)?;
+        let Some(path) = sanitize(Path::new(file.name())) else {
+            continue;
+        };
+        entries.push(Entry {
+            path,
+            is_dir: file.is_dir(),
+            size: file.size(),
+            compressed_size: Some(file.compressed_size()),
+            modified: file.last_modified().and_then(|time| {
+                let time = NaiveDate::from_ymd_opt(
+                    time.year().into(),
+                    time.month().into(),
+                    time.day().into(),
+                )?
+                .and_hms_opt(
+                    time.hour().into(),
+                    time.minute().into(),
+                    time.second().into(),
+                )?;
+                // Zip stores times without a timezone, by convention in local time
+                Local.from_local_datetime(&time).earliest()
+            }),
+            mode: file.unix_mode(),
+        });
+    }
+    Ok(entries)
+}
+
+fn list_tar(reader: impl Read) -> io::Result<Vec<Entry>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let Some(path) = sanitize(&entry.path()?) else {
+            continue;
+        };
+        let header = entry.header();
+        entries.push(Entry {
+            path,
+            is_dir: header.entry_type().is_dir(),
+            size: header.size()?,
+            compressed_size: None,
+            modified: header
+                .mtime()
+                .ok()
+                .and_then(|mtime| DateTime::from_timestamp(mtime as i64, 0))
+                .map(Into::into),
+            mode: header.mode().ok(),
+        });
+    }
+    Ok(entries)
+}
+
+/// The entries directly inside `dir`, including directories which only exist because other
+/// entries are inside them.
+pub fn children(entries: &[Entry], dir: &Path) -> Vec<Entry> {
+    let mut children = BTreeMap::new();
+    for entry in entries {
+        let Ok(rest) = entry.path.strip_prefix(dir) else {
+            continue;
+        };
+        let mut components = rest.components();
+        let Some(name) = components.next() else {
+            continue;
+        };
+
+        if components.next().is_none() {
+            children.insert(name.as_os_str().to_os_string(), entry.clone());
+        } else {
+            children
+                .entry(name.as_os_str().to_os_string())
+                .or_insert_with(|| Entry {
+                    path: dir.join(name),
+                    is_dir: true,
+                    size: 0,
+                    compressed_size: None,
+                    modified: None,
+                    mode: None,
+                });
+        }
+    }
+    children.into_values().collect()
+}
+
+fn cancelled_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "cancelled")
+}
+
+fn outside_error() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "would be extracted outside of the destination folder",
+    )
+}
+
+/// Something to add to a new archive.
+struct Source {
+    path: PathBuf,
+    /// Where it goes within the archive.
+    name: PathBuf,
+    metadata: fs::Metadata,
+}
+
+/// `sources` and everything inside of them, named relative to the folder they are in. Symlinks
+/// aren't followed.
+fn walk(sources: &[PathBuf]) -> io::Result<Vec<Source>> {
+    let mut stack = sources
+        .iter()
+        .rev()
+        .filter_map(|path| Some((path.clone(), PathBuf::from(path.file_name()?))))
+        .collect::<Vec<_>>();
+    let mut walked = Vec::new();
+    while let Some((path, name)) = stack.pop() {
+        let metadata = fs::symlink_metadata(&path)?;
+        if metadata.is_dir() {
+            let mut children = fs::read_dir(&path)?.collect::<io::Result<Vec<_>>>()?;
+            children.sort_by_key(|child| child.file_name());
+            for child in children.into_iter().rev() {
+                stack.push((child.path(), name.join(child.file_name())));
+            }
+        }
+        walked.push(Source {
+            path,
+            name,
+            metadata,
+        });
+    }
+    Ok(walked)
+}
+
+/// Create a new archive at `destination` containing `sources`, calling `progress` with how many
+/// entries have been added and the total. Nothing is left behind if this fails or is cancelled.
+pub fn compress(
+    sources: &[PathBuf],
+    destination: &Path,
+    format: Format,
+    level: Level,
+    cancelled: &AtomicBool,
+    mut progress: impl FnMut(usize, usize),
+) -> io::Result<()> {
+    let sources = walk(sources)?;
+    let file = BufWriter::new(File::create_new(destination)?);
+    let (sources, progress) = (sources.as_slice(), &mut progress);
+    let result = match format {
+        Format::Zip => write_zip(file, sources, level, cancelled, progress),
+        Format::Tar => write_tar(file, sources, cancelled, progress)?.flush(),
+        Format::TarGz => {
+            let encoder = GzEncoder::new(file, Compression::new(level.number()));
+            write_tar(encoder, sources, cancelled, progress)?
+                .finish()?
+                .flush()
+        }
+        Format::TarXz => {
+            let encoder = XzWriter::new(file, XzOptions::with_preset(level.number()))?;
+            write_tar(encoder, sources, cancelled, progress)?
+                .finish()?
+                .flush()
+        }
+        Format::TarZst => write_tar_zst(file, sources, cancelled, progress),
+    };
+    // Don't leave a broken archive behind
+    if result.is_err() {
+        fs::remove_file(destination).ok();
+    }
+    result
+}
+
+fn write_tar<W: Write>(
+    writer: W,
+    sources: &[Source],
+    cancelled: &AtomicBool,
+    progress: &mut impl FnMut(usize, usize),
+) -> io::Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    for (ix, source) in sources.iter().enumerate() {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(cancelled_error());
+        }
+        // This keeps the permissions and modification time
+        builder.append_path_with_name(&source.path, &source.name)?;
+        progress(ix + 1, sources.len());
+    }
+    builder.into_inner()
+}
+
+/// ruzstd can only compress from a reader, so the tar is written into a pipe which is compressed
+/// on another thread.
+fn write_tar_zst(
+    file: BufWriter<File>,
+    sources: &[Source],
+    cancelled: &AtomicBool,
+    progress: &mut impl FnMut(usize, usize),
+) -> io::Result<()> {
+    let (reader, writer) = io::pipe()?;
+    let compressor = thread::spawn(move || {
+        let mut reader = Stash::new(reader);
+        let mut file = Stash::new(file);
+        // ruzstd hasn't implemented any of the better levels yet, see `Format::has_levels`
+        let mut compressor = FrameCompressor::new(CompressionLevel::Fastest);
+        compressor.set_source(&mut reader);
+        compressor.set_drain(&mut file);
+        compressor.compress();
+        drop(compressor);
+        reader.into_result()?;
+        file.into_result()?.flush()
+    });
+
+    // Dropping the writer lets the compressor know the tar is finished
+    let result = write_tar(writer, sources, cancelled, progress).map(drop);
+    let compressed = compressor
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("failed to compress")));
+    result.and(compressed)
+}
+
+/// ruzstd panics when reading or writing fails, so this hides errors from it and keeps the first
+/// to be returned once it's done.
+struct Stash<T> {
+    inner: T,
+    error: Option<io::Error>,
+}
+
+impl<T> Stash<T> {
+    fn new(inner: T) -> Self {
+        Self { inner, error: None }
+    }
+
+    fn into_result(self) -> io::Result<T> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.inner),
+        }
+    }
+}
+
+impl<T: Read> Read for Stash<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.error.is_some() {
+            return Ok(0);
+        }
+        self.inner.read(buf).or_else(|err| {
+            self.error = Some(err);
+            Ok(0)
+        })
+    }
+}
+
+impl<T: Write> Write for Stash<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.error.is_none()
+            && let Err(err) = self.inner.write_all(buf)
+        {
+            self.error = Some(err);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.error.is_none()
+            && let Err(err) = self.inner.flush()
+        {
+            self.error = Some(err);
+        }
+        Ok(())
+    }
+}
+
+fn write_zip(
+    writer: impl Write + Seek,
+    sources: &[Source],
+    level: Level,
+    cancelled: &AtomicBool,
+    progress: &mut impl FnMut(usize, usize),
+) -> io::Result<()> {
+    let mut zip = ZipWriter::new(writer);
+    for (ix, source) in sources.iter().enumerate() {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(cancelled_error());
+        }
+
+        let mut options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .compression_level(Some(level.number().into()))
+            .unix_permissions(source.metadata.permissions().mode())
+            .large_file(source.metadata.len() >= u32::MAX.into());
+        if let Some(modified) = source.metadata.modified().ok().and_then(zip_time) {
+            options = options.last_modified_time(modified);
+        }
+
+        let file_type = source.metadata.file_type();
+        if file_type.is_dir() {
+            zip.add_directory_from_path(&source.name, options)?;
+        } else if file_type.is_symlink() {
+            // Not `add_symlink_from_path` as that drops any `..` from the target
+            let target = fs::read_link(&source.path)?;
+            zip.add_symlink(
+                source.name.to_string_lossy(),
+                target.to_string_lossy(),
+                options,
+            )?;
+        } else {
+            zip.start_file_from_path(&source.name, options)?;
+            io::copy(&mut File::open(&source.path)?, &mut zip)?;
+        }
+        progress(ix + 1, sources.len());
+    }
+    zip.finish()?.flush()
+}
+
+/// Zip stores times without a timezone, by convention in local time. It can't store anything
+/// before 1980.
+fn zip_time(time: std::time::SystemTime) -> Option<zip::DateTime> {
+    let time = DateTime::<Local>::from(time);
+    zip::DateTime::from_date_and_time(
+        time.year().try_into().ok()?,
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .ok()
+}
+
+/// Extract everything in the archive at `path` into the folder `destination`, creating it if
+/// needed. `progress` is called with how many entries have been extracted and the total.
+///
+/// Existing files are never overwritten and nothing is extracted outside of `destination`.
+/// Problems with a single entry are passed to `error` rather than stopping the extraction.
+pub fn extract(
+    path: &Path,
+    destination: &Path,
+    cancelled: &AtomicBool,
+    mut progress: impl FnMut(usize, usize),
+    mut error: impl FnMut(&Path, io::Error),
+) -> io::Result<()> {
+    let format = Format::detect(path)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not an archive"))?;
+    fs::create_dir_all(destination)?;
+    let destination = destination.canonicalize()?;
+    let file = BufReader::new(File::open(path)?);
+    let (progress, error) = (&mut progress, &mut error);
+
+    let reader: Box<dyn Read> = match format {
+        Format::Zip => return extract_zip(file, &destination, cancelled, progress, error),
+        Format::Tar => Box::new(file),
+        Format::TarGz => Box::new(GzDecoder::new(file)),
+        Format::TarXz => Box::new(XzReader::new(file, true)),
+        Format::TarZst => Box::new(StreamingDecoder::new(file).map_err(io::Error::other)?),
+    };
+    // Tar doesn't have an index so knowing how many entries there are takes an extra pass
+    let total = list(path)?.len();
+    extract_tar(reader, total, &destination, cancelled, progress, error)
+}
+
+fn extract_tar(
+    reader: impl Read,
+    total: usize,
+    destination: &Path,
+    cancelled: &AtomicBool,
+    progress: &mut impl FnMut(usize, usize),
+    error: &mut impl FnMut(&Path, io::Error),
+) -> io::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    archive.set_preserve_permissions(true);
+    archive.set_preserve_mtime(true);
+    archive.set_overwrite(false);
+
+    // Like `tar::Archive::unpack` directories are done last, so a read only directory doesn't
+    // stop what's inside of it from being extracted
+    let mut directories = Vec::new();
+    for (ix, entry) in archive.entries()?.enumerate() {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(cancelled_error());
+        }
+        let mut entry = entry?;
+        if entry.header().entry_type().is_dir() {
+            directories.push(entry);
+        } else {
+            unpack_tar_entry(&mut entry, destination, error);
+        }
+        progress((ix + 1).min(total), total);
+    }
+
+    directories.sort_by(|a, b| b.path_bytes().cmp(&a.path_bytes()));
+    for mut entry in directories {
+        unpack_tar_entry(&mut entry, destination, error);
+    }
+    Ok(())
+}
+
+fn unpack_tar_entry(
+    entry: &mut tar::Entry<impl Read>,
+    destination: &Path,
+    error: &mut impl FnMut(&Path, io::Error),
+) {
+    let path = entry
+        .path()
+        .map(|path| path.into_owned())
+        .unwrap_or_default();
+    // `unpack_in` refuses anything which would end up outside of `destination`, including
+    // through symlinks extracted earlier
+    match entry.unpack_in(destination) {
+        Ok(true) => {}
+        Ok(false) => error(&path, outside_error()),
+        Err(err) => {
+            // tar wraps the actual error in less helpful ones, eg. "failed to unpack `path`"
+            let mut cause = err.get_ref().and_then(|err| err.source());
+            while let Some(source) = cause.and_then(|cause| cause.source()) {
+                cause = Some(source);
+            }
+            match cause {
+                Some(cause) => error(&path, io::Error::new(err.kind(), cause.to_string())),
+                None => error(&path, err),
+            }
+        }
+    }
+}
+
+fn extract_zip(
+    file: impl Read + Seek,
+    destination: &Path,
+    cancelled: &AtomicBool,
+    progress: &mut impl FnMut(usize, usize),
+    error: &mut impl FnMut(&Path, io::Error),
+) -> io::Result<()> {
+    let mut archive = ZipArchive::new(file)?;
+    // Permissions are set last, so a read only directory doesn't stop what's inside of it from
+    // being extracted
+    let mut directories = Vec::new();
+    let total = archive.len();
+    for ix in 0..total {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(cancelled_error());
+        }
+        let mut file = archive.by_index(ix)?;
+        let name = PathBuf::from(file.name());
+        // Zip slip, eg. an entry named `../../.bashrc`
+        let result = match sanitize(&name) {
+            Some(path) => extract_zip_entry(
+                &mut file,
+                &destination.join(path),
+                destination,
+                &mut directories,
+            ),
+            None => Err(outside_error()),
+        };
+        if let Err(err) = result {
+            error(&name, err);
+        }
+        progress(ix + 1, total);
+    }
+
+    directories.sort_by(|(a, _), (b, _)| b.cmp(a));
+    for (path, mode) in directories {
+        if let Err(err) = fs::set_permissions(&path, fs::Permissions::from_mode(mode)) {
+            error(&path, err);
+        }
+    }
+    Ok(())
+}
+
+fn extract_zip_entry(
+    file: &mut zip::read::ZipFile,
+    path: &Path,
+    destination: &Path,
+    directories: &mut Vec<(PathBuf, u32)>,
+) -> io::Result<()> {
+    // Symlinks extracted earlier could point outside of the destination
+    let inside = path
+        .ancestors()
+        .find_map(|ancestor| ancestor.canonicalize().ok())
+        .is_some_and(|ancestor| ancestor.starts_with(destination));
+    if !inside {
+        return Err(outside_error());
+    }
+
+    let mode = file.unix_mode().map(|mode| mode & 0o777);
+    if file.is_dir() {
+        fs::create_dir_all(path)?;
+        directories.extend(mode.map(|mode| (path.to_path_buf(), mode)));
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if file.is_symlink() {
+        let mut target = String::new();
+        file.read_to_string(&mut target)?;
+        return std::os::unix::fs::symlink(target, path);
+    }
+
+    let mut output = File::create_new(path)?;
+    io::copy(file, &mut output)?;
+    if let Some(mode) = mode {
+        output.set_permissions(fs::Permissions::from_mode(mode))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every format, by cycling through them like the compress dialog does.
+    fn formats() -> Vec<Format> {
+        std::iter::successors(Some(Format::Zip), |format| {
+            Some(format.next()).filter(|format| *format != Format::Zip)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn detect_round_trips_the_extension() {
+        assert_eq!(formats().len(), 5);
+        for format in formats() {
+            let name = format!("Archive{}", format.extension());
+            assert_eq!(Format::detect(Path::new(&name)), Some(format), "{name}");
+            assert_eq!(stem(Path::new(&name)).as_deref(), Some("Archive"), "{name}");
+        }
+    }
+
+    #[test]
+    fn detect() {
+        assert_eq!(Format::detect(Path::new("a/b.TGZ")), Some(Format::TarGz));
+        assert_eq!(Format::detect(Path::new("b.txz")), Some(Format::TarXz));
+        assert_eq!(Format::detect(Path::new("b.tzst")), Some(Format::TarZst));
+        assert_eq!(Format::detect(Path::new("b.gz")), None);
+        assert_eq!(Format::detect(Path::new("zip")), None);
+        assert_eq!(
+            stem(Path::new("notes.v2.tar.gz")).as_deref(),
+            Some("notes.v2")
+        );
+        // Nothing is left without the extension
+        assert_eq!(stem(Path::new(".zip")), None);
+    }
+
+    #[test]
+    fn sanitize_keeps_entries_inside() {
+        assert_eq!(sanitize(Path::new("/a/./b")), Some(PathBuf::from("a/b")));
+        assert_eq!(sanitize(Path::new("a/../../b")), None);
+        assert_eq!(sanitize(Path::new("/")), None);
+    }
+
+    #[test]
+    fn children_adds_missing_directories() {
+        let entry = |path: &str, is_dir| Entry {
+            path: PathBuf::from(path),
+            is_dir,
+            size: 0,
+            compressed_size: None,
+            modified: None,
+            mode: None,
+        };
+        let entries = [
+            entry("a.txt", false),
+            entry("docs/b.txt", false),
+            entry("src/", true),
+            entry("src/main.rs", false),
+        ];
+
+        let root = children(&entries, Path::new(""))
+            .into_iter()
+            .map(|entry| (entry.path, entry.is_dir))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            root,
+            [
+                (PathBuf::from("a.txt"), false),
+                (PathBuf::from("docs"), true),
+                (PathBuf::from("src/"), true),
+            ]
+        );
+
+        let src = children(&entries, Path::new("src"));
+        assert_eq!(src.len(), 1);
+        assert_eq!(src[0].path, Path::new("src/main.rs"));
+    }
+
+    #[test]
+    fn compress_list_and_extract() {
+        let dir = std::env::temp_dir().join(format!("gpuidrive-archive-{}", std::process::id()));
+        let source = dir.join("source");
+        fs::create_dir_all(source.join("nested")).unwrap();
+        fs::write(source.join("a.txt"), "hello").unwrap();
+        fs::write(source.join("nested/b.txt"), "world").unwrap();
+
+        let cancelled = AtomicBool::new(false);
+        for format in formats() {
+            let path = dir.join(format!("test{}", format.extension()));
+            compress(
+                std::slice::from_ref(&source),
+                &path,
+                format,
+                Level::Fast,
+                &cancelled,
+                |_, _| {},
+            )
+            .unwrap();
+
+            let inside = path.join("source/nested");
+            let (archive, within) = split(&inside).unwrap();
+            assert_eq!(
+                (archive, within),
+                (path.as_path(), Path::new("source/nested"))
+            );
+
+            let entries = list(&path).unwrap();
+            let files = children(&entries, Path::new("source/nested"));
+            assert_eq!(files.len(), 1, "{format:?}");
+            assert_eq!(files[0].size, 5);
+
+            let destination = dir.join(format!("extracted-{}", format.label()));
+            extract(
+                &path,
+                &destination,
+                &cancelled,
+                |_, _| {},
+                |path, err| panic!("{}: {err}", path.display()),
+            )
+            .unwrap();
+            assert_eq!(
+                fs::read_to_string(destination.join("source/nested/b.txt")).unwrap(),
+                "world"
+            );
+            assert_eq!(
+                fs::read_to_string(destination.join("source/a.txt")).unwrap(),
+                "hello"
+            );
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+//! Cut/copy/paste of files.
+//!
+//! Other Linux file managers put files on the clipboard as `text/uri-list` and `x-special/gnome-copied-files`
+//! (the same list prefixed with a line containing `copy` or `cut`). gpui can only offer `text/plain`, so like
+//! Nautilus we write the GNOME representation as the clipboard text, prefixed with `x-special/nautilus-clipboard`.
+//! Nautilus pastes that as files. When pasting we understand both formats, but not plain paths so that text
+//! from "Copy Path" is never mistaken for files.
+
+use std::path::PathBuf;
+
+use gpui::{App, ClipboardItem};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::transfer::TransferKind;
+
+/// Files which have been cut or copied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileClipboard {
+    pub kind: TransferKind,
+    pub paths: Vec<PathBuf>,
+}
+
+/// The first line of the `text/plain` version of `x-special/gnome-copied-files` which Nautilus reads and writes.
+const NAUTILUS_HEADER: &str = "x-special/nautilus-clipboard";
+
+/// Stored as the clipboard metadata so we don't need to parse our own text.
+#[derive(Serialize, Deserialize)]
+struct Metadata {
+    kind: TransferKind,
+    gnome_copied_files: String,
+}
+
+impl FileClipboard {
+    pub fn write(&self, cx: &mut App) {
+        // TODO: Offer `text/uri-list` and `x-special/gnome-copied-files` as their own targets once gpui
+        // supports custom mime types
+        let gnome_copied_files = encode_gnome_copied_files(self.kind, &self.paths);
+        cx.write_to_clipboard(ClipboardItem::new_string_with_json_metadata(
+            format!("{NAUTILUS_HEADER}\n{gnome_copied_files}\n"),
+            Metadata {
+                kind: self.kind,
+                gnome_copied_files,
+            },
+        ));
+    }
+
+    pub fn read(cx: &mut App) -> Option<Self> {
+        let item = cx.read_from_clipboard()?;
+
+        for entry in item.entries() {
+            if let gpui::ClipboardEntry::String(string) = entry {
+                if let Some(metadata) = string.metadata_json::<Metadata>() {
+                    if let Some(clipboard) = decode(&metadata.gnome_copied_files) {
+                        return Some(Self {
+                            kind: metadata.kind,
+                            ..clipboard
+                        });
+                    }
+                }
+            }
+        }
+
+        decode(&item.text()?)
+    }
+}
+
+/// Encode paths in GNOME's `x-special/gnome-copied-files` format.
+pub fn encode_gnome_copied_files(kind: TransferKind, paths: &[PathBuf]) -> String {
+    let mut out = match kind {
+        TransferKind::Copy => "copy".to_string(),
+        TransferKind::Move => "cut".to_string(),
+    };
+    for url in paths
+        .iter()
+        .filter_map(|path| Url::from_file_path(path).ok())
+    {
+        out.push('\n');
+        out.push_str(url.as_str());
+    }
+    out
+}
+
+/// Decode files from clipboard text.
+///
+/// This understands `x-special/gnome-copied-files`, the `text/plain` version of it Nautilus writes and
+/// `text/uri-list`. Anything which isn't a `file://` URL means the text isn't files.
+pub fn decode(text: &str) -> Option<FileClipboard> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .peekable();
+
+    if lines.peek() == Some(&NAUTILUS_HEADER) {
+        lines.next();
+    }
+
+    let kind = match lines.peek() {
+        Some(&"cut") => {
+            lines.next();
+            TransferKind::Move
+        }
+        Some(&"copy") => {
+            lines.next();
+            TransferKind::Copy
+        }
+        _ => TransferKind::Copy,
+    };
+
+    let paths = lines
+        // Comments are allowed in `text/uri-list`
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| {
+            let url = Url::parse(line).ok()?;
+            if url.scheme() != "file" {
+                return None;
+            }
+            url.to_file_path().ok()
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    if paths.is_empty() {
+        return None;
+    }
+
+    Some(FileClipboard { kind, paths })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn decodes_gnome_copied_files() {
+        let clipboard =
+            decode("x-special/nautilus-clipboard\ncut\nfile:///tmp/a%20b\nfile:///tmp/c\n");
+        assert_eq!(
+            clipboard,
+            Some(FileClipboard {
+                kind: TransferKind::Move,
+                paths: paths(&["/tmp/a b", "/tmp/c"]),
+            })
+        );
+
+        let clipboard = decode("copy\nfile:///tmp/a").unwrap();
+        assert_eq!(clipboard.kind, TransferKind::Copy);
+    }
+
+    #[test]
+    fn decodes_uri_list() {
+        assert_eq!(
+            decode("# comment\r\nfile:///tmp/a\r\nfile:///tmp/b\r\n"),
+            Some(FileClipboard {
+                kind: TransferKind::Copy,
+                paths: paths(&["/tmp/a", "/tmp/b"]),
+            })
+        );
+    }
+
+    #[test]
+    fn ignores_plain_text() {
+        // What "Copy Path" writes
+        assert_eq!(decode("/tmp/a\n/tmp/b"), None);
+        assert_eq!(decode("https://example.com"), None);
+        assert_eq!(decode("file:///tmp/a\nhello"), None);
+        assert_eq!(decode(""), None);
+    }
+
+    #[test]
+    fn round_trips() {
+        let files = paths(&["/tmp/a", "/tmp/with space"]);
+        let text = format!(
+            "{NAUTILUS_HEADER}\n{}\n",
+            encode_gnome_copied_files(TransferKind::Move, &files)
+        );
+        assert_eq!(
+            decode(&text),
+            Some(FileClipboard {
+                kind: TransferKind::Move,
+                paths: files,
+            })
+        );
+    }
+}
@@ -1,17 +1,43 @@
+mod batch_rename;
 mod button;
+mod command_palette;
+mod compress_dialog;
+mod conflict_dialog;
+mod content_search;
 mod data_table;
+mod file_finder;
+mod filter_bar;
 mod icon;
 mod image;
+mod jump_list;
+mod menu;
 mod path_bar;
+mod picker;
+mod properties_panel;
 mod quick_preview;
+mod sidebar;
+mod status_bar;
 mod table_row;
 mod text_input;
 
+pub use batch_rename::*;
 pub use button::*;
+pub use command_palette::*;
+pub use compress_dialog::*;
+pub use conflict_dialog::*;
+pub use content_search::*;
 pub use data_table::*;
+pub use file_finder::*;
+pub use filter_bar::*;
 pub use icon::*;
 pub use image::*;
+pub use jump_list::*;
+pub use menu::*;
 pub use path_bar::*;
+pub use picker::*;
+pub use properties_panel::*;
 pub use quick_preview::*;
+pub use sidebar::*;
+pub use status_bar::*;
 pub use table_row::*;
 pub use text_input::*;
@@ -0,0 +1,352 @@
+use std::rc::Rc;
+
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::{
+    rename::{self, CaseConversion, RenameEntry, RenameOptions},
+    state::{Node, State},
+};
+
+use super::{OnChange, TextInput, button, button2};
+
+/// Dialog for renaming every selected node using a pattern.
+pub struct BatchRename {
+    state: Entity<State>,
+    focus_handle: FocusHandle,
+    /// Where focus should go back to when the dialog is closed.
+    previous_focus: Option<FocusHandle>,
+    nodes: Vec<Rc<Node>>,
+    find: Entity<TextInput>,
+    replace: Entity<TextInput>,
+    template: Entity<TextInput>,
+    counter_start: Entity<TextInput>,
+    regex: bool,
+    case: CaseConversion,
+    preview: Result<Vec<RenameEntry>, String>,
+    error: Option<String>,
+    open: bool,
+}
+
+impl BatchRename {
+    pub fn init(cx: &mut Context<Self>, state: Entity<State>) -> Self {
+        let defaults = RenameOptions::default();
+        let find = cx.new(|cx| TextInput::new(cx, "", "Find"));
+        let replace = cx.new(|cx| TextInput::new(cx, "", "Replace with"));
+        let template = cx.new(|cx| TextInput::new(cx, defaults.template, "Template"));
+        let counter_start =
+            cx.new(|cx| TextInput::new(cx, defaults.counter_start.to_string(), "Start at"));
+
+        for input in [&find, &replace, &template, &counter_start] {
+            cx.subscribe(input, |this, _, _: &OnChange, cx| this.update_preview(cx))
+                .detach();
+        }
+
+        Self {
+            state,
+            focus_handle: cx.focus_handle(),
+            previous_focus: None,
+            nodes: Vec::new(),
+            find,
+            replace,
+            template,
+            counter_start,
+            regex: defaults.regex,
+            case: defaults.case,
+            preview: Ok(Vec::new()),
+            error: None,
+            open: false,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Open the dialog for the current selection.
+    pub fn open(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.nodes = self.state.read(cx).selected_nodes();
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        self.open = true;
+        self.error = None;
+        self.previous_focus = window.focused(cx);
+        self.update_preview(cx);
+        self.find.read(cx).focus_handle.focus(window);
+    }
+
+    pub fn close(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.open = false;
+        self.nodes.clear();
+        if let Some(focus) = self.previous_focus.take() {
+            focus.focus(window);
+        }
+        cx.notify();
+    }
+
+    fn options(&self, cx: &App) -> RenameOptions {
+        RenameOptions {
+            find: self.find.read(cx).content.to_string(),
+            replace: self.replace.read(cx).content.to_string(),
+            regex: self.regex,
+            template: self.template.read(cx).content.to_string(),
+            counter_start: self
+                .counter_start
+                .read(cx)
+                .content
+                .parse()
+                .unwrap_or(RenameOptions::default().counter_start),
+            case: self.case,
+        }
+    }
+
+    fn update_preview(&mut self, cx: &mut Context<Self>) {
+        // TODO: This touches the filesystem for conflicts on every keystroke, should this be debounced?
+        self.preview = rename::preview(&self.nodes, &self.options(cx));
+        cx.notify();
+    }
+
+    fn can_apply(&self) -> bool {
+        match &self.preview {
+            Ok(entries) => {
+                entries.iter().all(|e| e.conflict.is_none())
+                    && entries.iter().any(|e| !e.is_unchanged())
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn apply(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Ok(entries) = &self.preview else {
+            return;
+        };
+        if !self.can_apply() {
+            return;
+        }
+
+        match rename::apply(entries) {
+            Ok(()) => {
+                let renamed = entries.iter().map(|e| e.to.clone()).collect::<Vec<_>>();
+                self.state.update(cx, |state, cx| {
+                    state.reload(cx);
+                    state.select_paths(cx, &renamed);
+                });
+                self.close(window, cx);
+            }
+            Err(err) => {
+                self.error = Some(format!("Nothing was renamed: {err}"));
+                self.update_preview(cx);
+            }
+        }
+    }
+
+    fn render_preview(&self) -> impl IntoElement {
+        let entries = match &self.preview {
+            Ok(entries) => entries.as_slice(),
+            Err(err) => {
+                return div()
+                    .id("batch-rename-preview")
+                    .h(px(300.))
+                    .p_2()
+                    .text_color(red())
+                    .child(err.clone());
+            }
+        };
+
+        div()
+            .id("batch-rename-preview")
+            .flex()
+            .flex_col()
+            .h(px(300.))
+            .overflow_y_scroll()
+            .border_1()
+            .border_color(rgb(0xE0E0E0))
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .px_2()
+                    .py_1()
+                    .text_xs()
+                    .text_color(rgb(0x555555))
+                    .bg(rgb(0xF0F0F0))
+                    .child(div().w(relative(0.45)).child("OLD NAME"))
+                    .child(div().w(relative(0.45)).child("NEW NAME"))
+                    .child(div().w(relative(0.1))),
+            )
+            .children(entries.iter().enumerate().map(|(i, entry)| {
+                div()
+                    .flex()
+                    .flex_row()
+                    .px_2()
+                    .py_0p5()
+                    .border_b_1()
+                    .border_color(rgb(0xE0E0E0))
+                    .bg(if entry.conflict.is_some() {
+                        rgb(0xFFE0E0)
+                    } else if i % 2 == 0 {
+                        rgb(0xFFFFFF)
+                    } else {
+                        rgb(0xFAFAFA)
+                    })
+                    .child(
+                        div()
+                            .w(relative(0.45))
+                            .truncate()
+                            .child(entry.old_name.clone()),
+                    )
+                    .child(
+                        div()
+                            .w(relative(0.45))
+                            .truncate()
+                            .when(entry.is_unchanged(), |this| this.text_color(rgb(0x999999)))
+                            .child(entry.new_name.clone()),
+                    )
+                    .child(
+                        div()
+                            .w(relative(0.1))
+                            .text_xs()
+                            .text_color(red())
+                            .children(entry.conflict.map(|c| c.label())),
+                    )
+            }))
+    }
+}
+
+fn labeled(label: &'static str, input: Entity<TextInput>) -> Div {
+    div()
+        .flex()
+        .flex_row()
+        .flex_1()
+        .items_center()
+        .gap_1()
+        .child(div().text_xs().text_color(rgb(0x555555)).child(label))
+        .child(
+            div()
+                .flex_1()
+                .border_1()
+                .border_color(rgb(0xE0E0E0))
+                .child(input),
+        )
+}
+
+impl Render for BatchRename {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !self.open {
+            return div().into_any();
+        }
+
+        let entity = cx.entity();
+
+        div()
+            .id("batch-rename")
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(|this, event: &KeyDownEvent, window, cx| {
+                match &*event.keystroke.key {
+                    "escape" => this.close(window, cx),
+                    "enter" => this.apply(window, cx),
+                    _ => {}
+                }
+            }))
+            .absolute()
+            .inset_0()
+            .size_full()
+            .flex()
+            .items_center()
+            .justify_center()
+            .bg(black().opacity(0.3))
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_2()
+                    .w(relative(0.6))
+                    .p_3()
+                    .bg(white())
+                    .text_sm()
+                    .rounded_md()
+                    .child(format!("Rename {} items", self.nodes.len()))
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .gap_2()
+                            .child(labeled("Find", self.find.clone()))
+                            .child(labeled("Replace", self.replace.clone()))
+                            .child(
+                                button(
+                                    if self.regex {
+                                        "Regex: On"
+                                    } else {
+                                        "Regex: Off"
+                                    },
+                                    {
+                                        let entity = entity.clone();
+                                        move |_, cx| {
+                                            entity.update(cx, |this, cx| {
+                                                this.regex = !this.regex;
+                                                this.update_preview(cx);
+                                            })
+                                        }
+                                    },
+                                )
+                                .when(self.regex, |this| this.bg(rgb(0xbababa))),
+                            ),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .gap_2()
+                            .child(labeled("Template", self.template.clone()))
+                            .child(
+                                div()
+                                    .w(px(120.))
+                                    .child(labeled("Start at", self.counter_start.clone())),
+                            )
+                            .child(button(self.case.label(), {
+                                let entity = entity.clone();
+                                move |_, cx| {
+                                    entity.update(cx, |this, cx| {
+                                        this.case = this.case.next();
+                                        this.update_preview(cx);
+                                    })
+                                }
+                            })),
+                    )
+                    .child(
+                        div()
+                            .text_xs()
+                            .text_color(rgb(0x777777))
+                            .child("Tokens: {name} {ext} {n} {n:3} {date} {date:%Y%m%d}"),
+                    )
+                    .child(self.render_preview())
+                    .children(
+                        self.error
+                            .clone()
+                            .map(|err| div().text_color(red()).child(err)),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .justify_end()
+                            .gap_2()
+                            .child(button("Cancel", {
+                                let entity = entity.clone();
+                                move |window, cx| {
+                                    entity.update(cx, |this, cx| this.close(window, cx))
+                                }
+                            }))
+                            .child(button2("Rename", !self.can_apply(), {
+                                let entity = entity.clone();
+                                move |window, cx| {
+                                    entity.update(cx, |this, cx| this.apply(window, cx))
+                                }
+                            })),
+                    ),
+            )
+            .into_any()
+    }
+}
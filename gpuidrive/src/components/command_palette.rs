@@ -0,0 +1,116 @@
+use gpui::*;
+
+use crate::{actions::ToggleCommandPalette, fuzzy::fuzzy_match, keymap};
+
+use super::{Picker, PickerDelegate, PickerItem};
+
+struct Command {
+    action: Box<dyn Action>,
+    /// eg. "Toggle Quick Preview" for `gpuidrive::ToggleQuickPreview`.
+    name: String,
+    shortcut: Option<String>,
+}
+
+/// Lists every action available in the focused view and dispatches the chosen one.
+#[derive(Default)]
+pub struct CommandPalette {
+    commands: Vec<Command>,
+    /// Indexes into `commands` and the highlighted characters of their names.
+    matches: Vec<(usize, Vec<usize>)>,
+}
+
+impl CommandPalette {
+    /// Collect the actions available where the focus is. This has to happen before the palette
+    /// takes focus.
+    pub fn collect_commands(&mut self, window: &Window, cx: &App) {
+        self.commands = window
+            .available_actions(cx)
+            .into_iter()
+            .filter(|action| !action.partial_eq(&ToggleCommandPalette))
+            .map(|action| Command {
+                name: humanize(action.name()),
+                shortcut: keymap::shortcut(&*action, window),
+                action,
+            })
+            .collect();
+        self.commands.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+}
+
+/// `gpuidrive::ToggleQuickPreview` to "Toggle Quick Preview" and `text_input::SelectAll` to
+/// "Text Input: Select All".
+fn humanize(name: &str) -> String {
+    let (namespace, name) = name.rsplit_once("::").unwrap_or(("", name));
+
+    let mut words = String::new();
+    for c in name.chars() {
+        if c.is_uppercase() && !words.is_empty() {
+            words.push(' ');
+        }
+        words.push(c);
+    }
+
+    match namespace {
+        "" | "gpuidrive" => words,
+        namespace => {
+            let namespace = namespace
+                .split('_')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars
+                        .next()
+                        .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!("{namespace}: {words}")
+        }
+    }
+}
+
+impl PickerDelegate for CommandPalette {
+    fn placeholder(&self) -> &'static str {
+        "Run a command"
+    }
+
+    fn update_matches(&mut self, query: String, _: &mut Context<Picker<Self>>) {
+        // There's only a few dozen actions so this doesn't need to be in the background
+        let mut matches = self
+            .commands
+            .iter()
+            .enumerate()
+            .filter_map(|(ix, command)| {
+                let (score, positions) = fuzzy_match(&command.name, &query)?;
+                Some((score, ix, positions))
+            })
+            .collect::<Vec<_>>();
+        if !query.is_empty() {
+            matches.sort_by(|(a, ..), (b, ..)| b.cmp(a));
+        }
+        self.matches = matches
+            .into_iter()
+            .map(|(_, ix, positions)| (ix, positions))
+            .collect();
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn render_match(&self, ix: usize) -> PickerItem {
+        let (command, positions) = &self.matches[ix];
+        let command = &self.commands[*command];
+        PickerItem {
+            label: command.name.clone().into(),
+            detail: command.shortcut.clone().map(Into::into),
+            highlights: positions.clone(),
+        }
+    }
+
+    fn confirm(&mut self, ix: usize, _: bool, window: &mut Window, cx: &mut Context<Picker<Self>>) {
+        // The picker has already given focus back so this goes to the view it was opened from
+        let (command, _) = &self.matches[ix];
+        window.dispatch_action(self.commands[*command].action.boxed_clone(), cx);
+    }
+}
@@ -0,0 +1,224 @@
+use std::path::PathBuf;
+
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::{
+    archive::{Format, Level},
+    compression::compress,
+    jobs::Jobs,
+    ops,
+    state::State,
+};
+
+use super::{OnChange, TextInput, button, button2};
+
+/// Dialog for compressing the selection into a new archive.
+pub struct CompressDialog {
+    state: Entity<State>,
+    jobs: Entity<Jobs>,
+    focus_handle: FocusHandle,
+    /// Where focus should go back to when the dialog is closed.
+    previous_focus: Option<FocusHandle>,
+    sources: Vec<PathBuf>,
+    /// The name of the archive, without the extension.
+    name: Entity<TextInput>,
+    format: Format,
+    level: Level,
+    open: bool,
+}
+
+impl CompressDialog {
+    pub fn init(cx: &mut Context<Self>, state: Entity<State>, jobs: Entity<Jobs>) -> Self {
+        let name = cx.new(|cx| TextInput::new(cx, "", "Name"));
+        // Whether the archive can be created depends on the name
+        cx.subscribe(&name, |_, _, _: &OnChange, cx| cx.notify())
+            .detach();
+
+        Self {
+            state,
+            jobs,
+            focus_handle: cx.focus_handle(),
+            previous_focus: None,
+            sources: Vec::new(),
+            name,
+            format: Format::Zip,
+            level: Level::default(),
+            open: false,
+        }
+    }
+
+    /// Open the dialog for the current selection.
+    pub fn open(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let state = self.state.read(cx);
+        if state.read_only() {
+            return;
+        }
+        self.sources = state
+            .selected_nodes()
+            .iter()
+            .map(|node| node.path.clone())
+            .collect();
+
+        // Like Finder a single item's archive is named after it
+        let name = match self.sources.as_slice() {
+            [] => return,
+            // TODO: Handle non-utf8 file names
+            [source] => source
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            _ => "Archive".to_string(),
+        };
+        self.name.update(cx, |input, cx| {
+            input.set_content(name, cx);
+            input.selected_range = 0..input.content.len();
+        });
+
+        self.open = true;
+        self.previous_focus = window.focused(cx);
+        self.name.read(cx).focus_handle.focus(window);
+        cx.notify();
+    }
+
+    pub fn close(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.open = false;
+        self.sources.clear();
+        if let Some(focus) = self.previous_focus.take() {
+            focus.focus(window);
+        }
+        cx.notify();
+    }
+
+    fn file_name(&self, cx: &App) -> Option<String> {
+        let name = self.name.read(cx).content.trim();
+        (!name.is_empty() && !name.contains('/'))
+            .then(|| format!("{name}{}", self.format.extension()))
+    }
+
+    fn apply(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(name) = self.file_name(cx) else {
+            return;
+        };
+
+        let dir = self.state.read(cx).path().clone();
+        compress(
+            cx,
+            self.jobs.clone(),
+            self.state.clone(),
+            std::mem::take(&mut self.sources),
+            ops::unique_path(&dir, &name),
+            self.format,
+            self.level,
+        );
+        self.close(window, cx);
+    }
+}
+
+impl Render for CompressDialog {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !self.open {
+            return div().into_any();
+        }
+
+        let entity = cx.entity();
+
+        div()
+            .id("compress-dialog")
+            .track_focus(&self.focus_handle)
+            .on_key_down(cx.listener(|this, event: &KeyDownEvent, window, cx| {
+                match &*event.keystroke.key {
+                    "escape" => this.close(window, cx),
+                    "enter" => this.apply(window, cx),
+                    _ => {}
+                }
+            }))
+            .absolute()
+            .inset_0()
+            .size_full()
+            .flex()
+            .items_center()
+            .justify_center()
+            .bg(black().opacity(0.3))
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_2()
+                    .w(px(420.))
+                    .p_3()
+                    .bg(white())
+                    .text_sm()
+                    .rounded_md()
+                    .child(format!("Compress {} items", self.sources.len()))
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .items_center()
+                            .gap_1()
+                            .child(
+                                div()
+                                    .flex_1()
+                                    .border_1()
+                                    .border_color(rgb(0xE0E0E0))
+                                    .child(self.name.clone()),
+                            )
+                            .child(
+                                div()
+                                    .text_color(rgb(0x555555))
+                                    .child(self.format.extension()),
+                            ),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .gap_2()
+                            .child(button(&format!("Format: {}", self.format.label()), {
+                                let entity = entity.clone();
+                                move |_, cx| {
+                                    entity.update(cx, |this, cx| {
+                                        this.format = this.format.next();
+                                        cx.notify();
+                                    })
+                                }
+                            }))
+                            .when(self.format.has_levels(), |this| {
+                                this.child(button(
+                                    &format!("Compression: {}", self.level.label()),
+                                    {
+                                        let entity = entity.clone();
+                                        move |_, cx| {
+                                            entity.update(cx, |this, cx| {
+                                                this.level = this.level.next();
+                                                cx.notify();
+                                            })
+                                        }
+                                    },
+                                ))
+                            }),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .justify_end()
+                            .gap_2()
+                            .child(button("Cancel", {
+                                let entity = entity.clone();
+                                move |window, cx| {
+                                    entity.update(cx, |this, cx| this.close(window, cx))
+                                }
+                            }))
+                            .child(button2("Compress", self.file_name(cx).is_none(), {
+                                let entity = entity.clone();
+                                move |window, cx| {
+                                    entity.update(cx, |this, cx| this.apply(window, cx))
+                                }
+                            })),
+                    ),
+            )
+            .into_any()
+    }
+}
@@ -0,0 +1,206 @@
+use std::{collections::VecDeque, path::PathBuf};
+
+use futures::channel::oneshot;
+use gpui::{prelude::FluentBuilder, *};
+use human_bytes::human_bytes;
+
+use crate::{
+    state::{Node, NodeKind},
+    transfer::{ConflictAnswer, Resolution},
+};
+
+use super::button;
+
+struct Conflict {
+    source: Option<Node>,
+    destination: Option<Node>,
+    tx: oneshot::Sender<ConflictAnswer>,
+}
+
+/// Asks the user what to do when a transfer's destination already exists.
+///
+/// Multiple transfers can be running at once so conflicts are queued and shown one at a time.
+pub struct ConflictDialog {
+    conflicts: VecDeque<Conflict>,
+    apply_to_all: bool,
+}
+
+impl ConflictDialog {
+    pub fn init() -> Self {
+        Self {
+            conflicts: VecDeque::new(),
+            apply_to_all: false,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+
+    pub fn ask(
+        &mut self,
+        cx: &mut Context<Self>,
+        source: PathBuf,
+        destination: PathBuf,
+    ) -> oneshot::Receiver<ConflictAnswer> {
+        let (tx, rx) = oneshot::channel();
+        self.conflicts.push_back(Conflict {
+            source: Node::from_path(source).ok(),
+            destination: Node::from_path(destination).ok(),
+            tx,
+        });
+        cx.notify();
+        rx
+    }
+
+    fn answer(&mut self, cx: &mut Context<Self>, resolution: Resolution) {
+        if let Some(conflict) = self.conflicts.pop_front() {
+            conflict
+                .tx
+                .send(ConflictAnswer {
+                    resolution,
+                    apply_to_all: self.apply_to_all,
+                })
+                .ok();
+        }
+        self.apply_to_all = false;
+        cx.notify();
+    }
+}
+
+fn render_node(title: &'static str, node: Option<&Node>) -> Div {
+    let Some(node) = node else {
+        return div().flex_1().child(title).child("Unknown");
+    };
+
+    let thumbnail = match node.path.extension().and_then(|ext| ext.to_str()) {
+        // TODO: Use gpui::Img::extensions() for image detection
+        Some("png" | "jpg" | "jpeg" | "gif" | "webp" | "bmp" | "svg")
+            if node.kind == NodeKind::File =>
+        {
+            img(node.path.clone()).size(px(64.)).into_any_element()
+        }
+        _ => div()
+            .flex()
+            .items_center()
+            .justify_center()
+            .size(px(64.))
+            .bg(rgb(0xF0F0F0))
+            .text_xs()
+            .child(format!("{:?}", node.kind))
+            .into_any_element(),
+    };
+
+    div()
+        .flex()
+        .flex_col()
+        .flex_1()
+        .gap_1()
+        .child(div().text_xs().text_color(rgb(0x555555)).child(title))
+        .child(
+            div().flex().flex_row().gap_2().child(thumbnail).child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .child(
+                        div()
+                            .truncate()
+                            .child(node.path.to_string_lossy().to_string()),
+                    )
+                    .child(match node.kind {
+                        NodeKind::Directory => "Folder".to_string(),
+                        _ => human_bytes(node.size as f64), // TODO: This cast is bad
+                    })
+                    .child(format!(
+                        "Modified {}",
+                        node.modified.format("%B %d, %Y %H:%M")
+                    )),
+            ),
+        )
+}
+
+impl Render for ConflictDialog {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let Some(conflict) = self.conflicts.front() else {
+            return div().into_any();
+        };
+
+        let is_dir = |node: &Option<Node>| {
+            node.as_ref()
+                .is_some_and(|node| node.kind == NodeKind::Directory)
+        };
+        let both_dirs = is_dir(&conflict.source) && is_dir(&conflict.destination);
+        let name = conflict
+            .destination
+            .as_ref()
+            .map(|node| node.name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let entity = cx.entity();
+        let choice = |label: &'static str, resolution: Resolution| {
+            let entity = entity.clone();
+            button(label, move |_, cx| {
+                entity.update(cx, |this, cx| this.answer(cx, resolution))
+            })
+        };
+
+        div()
+            .id("conflict-dialog")
+            .occlude()
+            .absolute()
+            .inset_0()
+            .size_full()
+            .flex()
+            .items_center()
+            .justify_center()
+            .bg(black().opacity(0.3))
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap_3()
+                    .w(relative(0.5))
+                    .p_3()
+                    .bg(white())
+                    .text_sm()
+                    .rounded_md()
+                    .child(format!("\"{name}\" already exists"))
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .gap_4()
+                            .child(render_node("Existing", conflict.destination.as_ref()))
+                            .child(render_node("New", conflict.source.as_ref())),
+                    )
+                    .child(
+                        div()
+                            .id("apply-to-all")
+                            .flex()
+                            .flex_row()
+                            .gap_1()
+                            .cursor_pointer()
+                            .child(if self.apply_to_all { "[x]" } else { "[ ]" })
+                            .child("Apply to all remaining conflicts")
+                            .on_click(cx.listener(|this, _, _, cx| {
+                                this.apply_to_all = !this.apply_to_all;
+                                cx.notify();
+                            })),
+                    )
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .justify_end()
+                            .gap_2()
+                            .child(choice("Cancel", Resolution::Cancel))
+                            .child(choice("Skip", Resolution::Skip))
+                            .child(choice("Keep Both", Resolution::KeepBoth))
+                            .when(both_dirs, |this| {
+                                this.child(choice("Merge", Resolution::Merge))
+                            })
+                            .child(choice("Replace", Resolution::Replace)),
+                    ),
+            )
+            .into_any()
+    }
+}
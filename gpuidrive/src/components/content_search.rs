@@ -0,0 +1,356 @@
+use std::{
+    path::PathBuf,
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use futures::{StreamExt, channel::mpsc};
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::{
+    grep::{self, FileMatches},
+    state::{Node, PathChange, State},
+};
+
+use super::{QuickPreview, TextInput, button};
+
+/// Stop searching once this many lines have matched, rendering more isn't useful.
+const MAX_MATCHES: usize = 2000;
+/// How often the results are updated while the search is running.
+const BATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Searches the contents of the files below the current directory.
+pub struct ContentSearch {
+    state: Entity<State>,
+    quick_preview: Entity<QuickPreview>,
+    input: Entity<TextInput>,
+    is_regex: bool,
+    /// The directory the results were found in.
+    root: PathBuf,
+    results: Vec<FileMatches>,
+    match_count: usize,
+    error: Option<String>,
+    /// Set to stop the running search.
+    cancelled: Arc<AtomicBool>,
+    searching: bool,
+    task: Option<Task<()>>,
+    open: bool,
+    /// Where focus should go back to when the panel is closed.
+    previous_focus: Option<FocusHandle>,
+}
+
+impl ContentSearch {
+    pub fn init(
+        cx: &mut Context<Self>,
+        state: Entity<State>,
+        quick_preview: Entity<QuickPreview>,
+    ) -> Self {
+        cx.subscribe(&state, |this, _, _: &PathChange, cx| {
+            this.cancel();
+            this.results.clear();
+            this.match_count = 0;
+            cx.notify();
+        })
+        .detach();
+
+        Self {
+            state,
+            quick_preview,
+            input: cx.new(|cx| TextInput::new(cx, "", "Search file contents")),
+            is_regex: false,
+            root: PathBuf::new(),
+            results: Vec::new(),
+            match_count: 0,
+            error: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+            searching: false,
+            task: None,
+            open: false,
+            previous_focus: None,
+        }
+    }
+
+    pub fn toggle(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.open {
+            self.close(window, cx);
+        } else {
+            self.open = true;
+            self.previous_focus = window.focused(cx);
+            self.input.read(cx).focus_handle.focus(window);
+            cx.notify();
+        }
+    }
+
+    fn close(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.open = false;
+        self.cancel();
+        if let Some(focus) = self.previous_focus.take() {
+            focus.focus(window);
+        }
+        cx.notify();
+    }
+
+    fn cancel(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.searching = false;
+        self.task = None;
+    }
+
+    fn search(&mut self, cx: &mut Context<Self>) {
+        self.cancel();
+        self.results.clear();
+        self.match_count = 0;
+        self.error = None;
+        cx.notify();
+
+        let text = self.input.read(cx).content.to_string();
+        if text.is_empty() {
+            return;
+        }
+        let regex = match grep::query(&text, self.is_regex) {
+            Ok(regex) => regex,
+            Err(err) => {
+                self.error = Some(err.to_string());
+                return;
+            }
+        };
+
+        let state = self.state.read(cx);
+        if state.read_only() {
+            self.error = Some("Contents can't be searched inside archives".to_string());
+            return;
+        }
+        let (root, show_hidden) = (state.path().clone(), state.show_hidden());
+        self.root = root.clone();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.cancelled = cancelled.clone();
+        self.searching = true;
+
+        let (tx, mut rx) = mpsc::unbounded();
+        cx.background_executor()
+            .spawn({
+                let cancelled = cancelled.clone();
+                async move {
+                    grep::grep(&root, &regex, show_hidden, &cancelled, |matches| {
+                        tx.unbounded_send(matches).ok();
+                    });
+                }
+            })
+            .detach();
+
+        self.task = Some(cx.spawn(async move |this, cx| {
+            while let Some(matches) = rx.next().await {
+                let mut results = vec![matches];
+                while let Ok(Some(matches)) = rx.try_next() {
+                    results.push(matches);
+                }
+
+                let result = this.update(cx, |this, cx| {
+                    this.match_count += results.iter().map(|m| m.lines.len()).sum::<usize>();
+                    this.results.extend(results);
+                    if this.match_count >= MAX_MATCHES {
+                        cancelled.store(true, Ordering::Relaxed);
+                    }
+                    cx.notify();
+                });
+                if result.is_err() {
+                    return;
+                }
+                cx.background_executor().timer(BATCH_INTERVAL).await;
+            }
+
+            this.update(cx, |this, cx| {
+                this.searching = false;
+                cx.notify();
+            })
+            .ok();
+        }));
+    }
+
+    fn render_file(
+        &self,
+        first: usize,
+        file: &FileMatches,
+        window: &Window,
+        cx: &Context<Self>,
+    ) -> Div {
+        let relative = file.path.strip_prefix(&self.root).unwrap_or(&file.path);
+
+        div()
+            .flex()
+            .flex_col()
+            .child(
+                div()
+                    .font_weight(FontWeight::BOLD)
+                    .truncate()
+                    .child(relative.to_string_lossy().to_string()),
+            )
+            .children(file.lines.iter().enumerate().map(|(i, line)| {
+                let highlights = line
+                    .ranges
+                    .iter()
+                    .map(|range| {
+                        (
+                            range.clone(),
+                            HighlightStyle {
+                                font_weight: Some(FontWeight::BOLD),
+                                background_color: Some(rgba(0xffe06680).into()),
+                                ..Default::default()
+                            },
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                div()
+                    .id(("match", first + i))
+                    .flex()
+                    .flex_row()
+                    .gap_2()
+                    .cursor_pointer()
+                    .hover(|this| this.bg(rgb(0xF0F0F0)))
+                    .child(
+                        div()
+                            .flex_none()
+                            .w(px(40.))
+                            .flex()
+                            .justify_end()
+                            .text_color(rgb(0x999999))
+                            .child((line.line + 1).to_string()),
+                    )
+                    .child(
+                        div().truncate().child(
+                            StyledText::new(line.snippet.clone())
+                                .with_default_highlights(&window.text_style(), highlights),
+                        ),
+                    )
+                    .on_click({
+                        let path = file.path.clone();
+                        let line = line.line;
+                        let quick_preview = self.quick_preview.clone();
+                        let entity = cx.entity();
+                        move |_, window, cx| match Node::from_path(path.clone()) {
+                            Ok(node) => quick_preview.update(cx, |quick_preview, cx| {
+                                quick_preview.show_line(Rc::new(node), line, window, cx)
+                            }),
+                            // eg. the file was deleted since it was searched
+                            Err(err) => entity.update(cx, |this, cx| {
+                                this.error =
+                                    Some(format!("Failed to preview {}: {err}", path.display()));
+                                cx.notify();
+                            }),
+                        }
+                    })
+            }))
+    }
+}
+
+impl Render for ContentSearch {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !self.open {
+            return div().into_any();
+        }
+
+        let status = if let Some(err) = &self.error {
+            div().text_color(red()).child(err.clone())
+        } else {
+            let count = format!(
+                "{} matches in {} files",
+                self.match_count,
+                self.results.len()
+            );
+            div()
+                .text_xs()
+                .text_color(rgb(0x555555))
+                .child(if self.searching {
+                    format!("Searching… {count}")
+                } else if self.match_count >= MAX_MATCHES {
+                    format!("{count}, stopped after {MAX_MATCHES} matches")
+                } else {
+                    count
+                })
+        };
+
+        let mut first = 0;
+        div()
+            .id("content-search")
+            .flex()
+            .flex_col()
+            .flex_none()
+            .gap_2()
+            .w(px(480.))
+            .h_full()
+            .p_2()
+            .overflow_y_scroll()
+            .bg(white())
+            .text_sm()
+            .border_l_1()
+            .border_color(rgb(0xE0E0E0))
+            .on_key_down(cx.listener(|this, event: &KeyDownEvent, window, cx| {
+                match &*event.keystroke.key {
+                    "enter" => this.search(cx),
+                    "escape" => this.close(window, cx),
+                    _ => {}
+                }
+            }))
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .justify_between()
+                    .child("Search Contents")
+                    .child(button("Close", {
+                        let entity = cx.entity();
+                        move |window, cx| entity.update(cx, |this, cx| this.close(window, cx))
+                    })),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .gap_2()
+                    .child(
+                        div()
+                            .flex_1()
+                            .border_1()
+                            .border_color(rgb(0x3311ff))
+                            .child(self.input.clone()),
+                    )
+                    .child(button(if self.is_regex { "Regex" } else { "Text" }, {
+                        let entity = cx.entity();
+                        move |_, cx| {
+                            entity.update(cx, |this, cx| {
+                                this.is_regex = !this.is_regex;
+                                cx.notify();
+                            })
+                        }
+                    }))
+                    .child(button("Search", {
+                        let entity = cx.entity();
+                        move |_, cx| entity.update(cx, |this, cx| this.search(cx))
+                    }))
+                    .when(self.searching, |this| {
+                        this.child(button("Stop", {
+                            let entity = cx.entity();
+                            move |_, cx| {
+                                entity.update(cx, |this, cx| {
+                                    this.cancelled.store(true, Ordering::Relaxed);
+                                    cx.notify();
+                                })
+                            }
+                        }))
+                    }),
+            )
+            .child(status)
+            .children(self.results.iter().map(|file| {
+                let element = self.render_file(first, file, window, cx);
+                first += file.lines.len();
+                element
+            }))
+            .into_any()
+    }
+}
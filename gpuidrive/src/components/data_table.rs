@@ -1,35 +1,621 @@
-use std::{ops::Range, rc::Rc};
+use std::{
+    ops::Range,
+    path::PathBuf,
+    rc::Rc,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
 
-use gpui::*;
+use gpui::{prelude::FluentBuilder, *};
 
-use crate::state::{FocusSelection, State};
+use crate::{
+    actions::{
+        Compress, CopyFiles, CutFiles, ExtractHere, ExtractToFolder, MoveToTrash, NewFolder,
+        PasteFiles, Rename, SelectPageDown, SelectPageUp, ToggleHiddenFiles, ToggleProperties,
+    },
+    archive::{self, Format},
+    clipboard::FileClipboard,
+    compression::extract,
+    jobs::Jobs,
+    ops::{self, NewItem},
+    state::{FocusSelection, NodeKind, PathChange, State},
+    transfer::{TransferKind, transfer},
+};
 
-use super::{TableRow, open_node, render_titles};
+use super::{
+    ConflictDialog, Menu, MenuItem, OnDrop, TableRow, TextInput, open_node, render_titles,
+};
 
 const SCROLLBAR_THUMB_WIDTH: Pixels = px(8.);
 const SCROLLBAR_THUMB_HEIGHT: Pixels = px(100.);
+/// How long after the last keystroke type-ahead starts a new search.
+const TYPE_AHEAD_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// The nodes being dragged out of the table. This is also the drag preview.
+#[derive(Clone)]
+pub struct DraggedNodes {
+    pub paths: Vec<PathBuf>,
+}
+
+impl Render for DraggedNodes {
+    fn render(&mut self, _: &mut Window, _: &mut Context<Self>) -> impl IntoElement {
+        let count = self.paths.len();
+
+        div()
+            .px_2()
+            .py_1()
+            .bg(white())
+            .text_sm()
+            .border_1()
+            .border_color(rgb(0xE0E0E0))
+            .rounded_md()
+            .shadow_md()
+            .child(format!(
+                "{count} {}",
+                if count == 1 { "item" } else { "items" }
+            ))
+    }
+}
+
+/// Dragging moves nodes, holding ctrl or alt copies them instead.
+pub fn drag_kind(window: &Window) -> TransferKind {
+    let modifiers = window.modifiers();
+    if modifiers.control || modifiers.alt {
+        TransferKind::Copy
+    } else {
+        TransferKind::Move
+    }
+}
+
+/// Emitted when the user asks for the properties of the selection.
+pub struct ShowProperties;
 
 pub struct DataTable {
     state: Entity<State>,
+    jobs: Entity<Jobs>,
+    conflict_dialog: Entity<ConflictDialog>,
+    pub focus_handle: FocusHandle,
     /// Use `Rc` to share the same quote data across multiple items, avoid cloning.
     // nodes: Vec<Rc<Node>>,
     visible_range: Range<usize>,
     scroll: UniformListScrollHandle,
     /// The position in thumb bounds when dragging start mouse down.
     drag_position: Option<Point<Pixels>>,
+    /// The row being renamed inline and the input for its new name.
+    renaming: Option<(usize, Entity<TextInput>)>,
+    rename_error: Option<String>,
+    /// Where focus should go back to when renaming is finished.
+    previous_focus: Option<FocusHandle>,
+    /// The position of the right-click menu, if it's open.
+    context_menu: Option<Point<Pixels>>,
+    /// What the user has typed to jump to a row and when they last typed.
+    type_ahead: String,
+    type_ahead_at: Option<Instant>,
 }
 
 impl DataTable {
-    pub fn new(state: Entity<State>) -> Self {
+    pub fn new(
+        cx: &mut Context<Self>,
+        state: Entity<State>,
+        jobs: Entity<Jobs>,
+        conflict_dialog: Entity<ConflictDialog>,
+    ) -> Self {
+        cx.subscribe(&state, |this, _, _: &PathChange, cx| {
+            this.renaming = None;
+            this.rename_error = None;
+            this.context_menu = None;
+            cx.notify();
+        })
+        .detach();
+        // The key context depends on if a conflict is being shown
+        cx.observe(&conflict_dialog, |_, _, cx| cx.notify())
+            .detach();
+
         Self {
             state,
+            jobs,
+            conflict_dialog,
+            focus_handle: cx.focus_handle(),
             // nodes: Vec::new(),
             visible_range: 0..0,
             scroll: UniformListScrollHandle::new(),
             drag_position: None,
+            renaming: None,
+            rename_error: None,
+            previous_focus: None,
+            context_menu: None,
+            type_ahead: String::new(),
+            type_ahead_at: None,
+        }
+    }
+
+    /// The number of rows which fit in the table, for PageUp/PageDown.
+    fn page_size(&self) -> usize {
+        self.visible_range.len().max(1)
+    }
+
+    fn select_page_up(&mut self, _: &SelectPageUp, _: &mut Window, cx: &mut Context<Self>) {
+        let delta = -(self.page_size() as isize);
+        self.state
+            .update(cx, |state, cx| state.move_selection(cx, delta));
+    }
+
+    fn select_page_down(&mut self, _: &SelectPageDown, _: &mut Window, cx: &mut Context<Self>) {
+        let delta = self.page_size() as isize;
+        self.state
+            .update(cx, |state, cx| state.move_selection(cx, delta));
+    }
+
+    /// Select the first row starting with what the user has typed recently.
+    fn type_ahead(&mut self, text: &str, cx: &mut Context<Self>) {
+        let now = Instant::now();
+        if self
+            .type_ahead_at
+            .is_none_or(|at| now.duration_since(at) > TYPE_AHEAD_TIMEOUT)
+        {
+            self.type_ahead.clear();
+        }
+        self.type_ahead.push_str(&text.to_lowercase());
+        self.type_ahead_at = Some(now);
+
+        let query = &self.type_ahead;
+        self.state.update(cx, |state, cx| {
+            let ix = state.nodes().iter().position(|node| {
+                // TODO: Handle non-utf8 file names
+                node.name
+                    .to_string_lossy()
+                    .to_lowercase()
+                    .starts_with(query.as_str())
+            });
+            if let Some(ix) = ix {
+                // This emits `FocusSelection` which scrolls to the row
+                state.set_selection(cx, ix);
+            }
+        });
+    }
+
+    /// What the user has typed recently, so they can carry on with it in the filter.
+    pub fn take_type_ahead(&mut self) -> Option<String> {
+        let recent = self
+            .type_ahead_at
+            .take()
+            .is_some_and(|at| at.elapsed() <= TYPE_AHEAD_TIMEOUT);
+        let text = std::mem::take(&mut self.type_ahead);
+        recent.then_some(text)
+    }
+
+    fn selected_paths(&self, cx: &App) -> Vec<PathBuf> {
+        self.state
+            .read(cx)
+            .selected_nodes()
+            .iter()
+            .map(|node| node.path.clone())
+            .collect()
+    }
+
+    /// Put the selected nodes on the clipboard.
+    fn copy_selected(&mut self, kind: TransferKind, cx: &mut Context<Self>) {
+        // TODO: Extract files from archives when they're pasted
+        if self.state.read(cx).read_only() {
+            return;
+        }
+        let paths = self.selected_paths(cx);
+        if !paths.is_empty() {
+            FileClipboard { kind, paths }.write(cx);
+        }
+    }
+
+    /// Transfer the files on the clipboard into the current directory.
+    fn paste(&mut self, cx: &mut Context<Self>) {
+        if self.state.read(cx).read_only() {
+            return;
+        }
+        let Some(clipboard) = FileClipboard::read(cx) else {
+            return;
+        };
+        if clipboard.kind == TransferKind::Move {
+            // Like other file managers a cut can only be pasted once
+            cx.write_to_clipboard(ClipboardItem::new_string(String::new()));
+        }
+
+        transfer(
+            cx,
+            self.jobs.clone(),
+            self.state.clone(),
+            self.conflict_dialog.clone(),
+            clipboard.kind,
+            clipboard.paths,
+            self.state.read(cx).path().clone(),
+        );
+    }
+
+    fn trash_selected(&mut self, cx: &mut Context<Self>) {
+        let paths = self.selected_paths(cx);
+        if paths.is_empty() || self.state.read(cx).read_only() {
+            return;
+        }
+
+        let jobs = self.jobs.clone();
+        let state = self.state.clone();
+        let (id, cancelled) = jobs.update(cx, |jobs, cx| {
+            jobs.start(
+                cx,
+                format!("Moving {} items to the trash", paths.len()),
+                paths.len(),
+            )
+        });
+
+        cx.spawn(async move |_, cx| {
+            // Each item is trashed on its own so one failure doesn't hold up the rest
+            let total = paths.len();
+            for (i, path) in paths.into_iter().enumerate() {
+                if cancelled.load(Ordering::Relaxed) {
+                    break;
+                }
+                let result = cx
+                    .background_executor()
+                    .spawn({
+                        let path = path.clone();
+                        async move { trash::delete(path) }
+                    })
+                    .await;
+                jobs.update(cx, |jobs, cx| {
+                    if let Err(err) = result {
+                        jobs.error(cx, id, format!("{}: {err}", path.display()));
+                    }
+                    jobs.progress(cx, id, i + 1, total);
+                })
+                .ok();
+            }
+
+            jobs.update(cx, |jobs, cx| jobs.finish(cx, id)).ok();
+            state.update(cx, |state, cx| state.reload(cx)).ok();
+        })
+        .detach();
+    }
+
+    /// Extract the selected archive into the current directory, or a new folder named after it.
+    fn extract_selected(&mut self, into_folder: bool, cx: &mut Context<Self>) {
+        let state = self.state.read(cx);
+        let nodes = state.selected_nodes();
+        let [node] = nodes.as_slice() else {
+            return;
+        };
+        let is_archive = node.kind == NodeKind::File && Format::detect(&node.path).is_some();
+        if !is_archive || state.read_only() {
+            return;
+        }
+
+        let dir = state.path().clone();
+        let destination = match archive::stem(&node.path) {
+            Some(stem) if into_folder => ops::unique_path(&dir, &stem),
+            _ => dir,
+        };
+        extract(
+            cx,
+            self.jobs.clone(),
+            self.state.clone(),
+            node.path.clone(),
+            destination,
+        );
+    }
+
+    pub fn copy_selected_paths(&mut self, cx: &mut Context<Self>) {
+        let paths = self.selected_paths(cx);
+        if !paths.is_empty() {
+            // TODO: Handle non-utf8 paths
+            cx.write_to_clipboard(ClipboardItem::new_string(
+                paths
+                    .iter()
+                    .map(|path| path.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ));
+        }
+    }
+
+    /// Open the right-click menu for the row at `ix`, or the empty space in the table if `None`.
+    fn deploy_context_menu(
+        &mut self,
+        position: Point<Pixels>,
+        ix: Option<usize>,
+        cx: &mut Context<Self>,
+    ) {
+        self.state.update(cx, |state, cx| match ix {
+            // Right-clicking within the selection keeps it like Finder
+            Some(ix) if state.is_selected(ix) => {}
+            Some(ix) => state.set_selection(cx, ix),
+            None => state.clear_selection(cx),
+        });
+        self.context_menu = Some(position);
+        cx.notify();
+    }
+
+    fn render_context_menu(
+        &self,
+        position: Point<Pixels>,
+        window: &Window,
+        cx: &mut Context<Self>,
+    ) -> Menu {
+        let state = self.state.read(cx);
+        let nodes = state.selected_nodes();
+        let read_only = state.read_only();
+        let writable = !read_only && ops::is_writable(state.path());
+        let show_hidden = state.show_hidden();
+        let has_clipboard = FileClipboard::read(cx).is_some();
+        let single = match nodes.as_slice() {
+            [node] if node.kind != NodeKind::Unknown => Some(node.clone()),
+            _ => None,
+        };
+        let is_archive = single.as_ref().is_some_and(|node| {
+            node.kind == NodeKind::File && Format::detect(&node.path).is_some()
+        });
+
+        let entity = cx.entity();
+        let action = |f: fn(&mut Self, &mut Window, &mut Context<Self>)| {
+            let entity = entity.clone();
+            move |window: &mut Window, cx: &mut App| {
+                entity.update(cx, |this, cx| f(this, window, cx))
+            }
+        };
+
+        let mut items = Vec::new();
+        if !nodes.is_empty() {
+            items.extend([
+                MenuItem::entry("Open", {
+                    let state = self.state.clone();
+                    let single = single.clone();
+                    move |_, cx| {
+                        if let Some(node) = &single {
+                            open_node(&state, cx, node, false);
+                        }
+                    }
+                })
+                .disabled(single.is_none()),
+                // TODO: List the applications which can open the file
+                MenuItem::entry("Open With…", |_, _| {}).disabled(true),
+                // TODO: Enable this once we have tabs
+                MenuItem::entry("Open in New Tab", |_, _| {}).disabled(true),
+                MenuItem::Separator,
+                MenuItem::entry(
+                    "Rename",
+                    action(|this, window, cx| this.rename_selected(window, cx)),
+                )
+                .action_shortcut(&Rename, window)
+                .disabled(single.is_none() || !writable),
+                MenuItem::entry(
+                    "Copy",
+                    action(|this, _, cx| this.copy_selected(TransferKind::Copy, cx)),
+                )
+                .action_shortcut(&CopyFiles, window)
+                .disabled(read_only),
+                MenuItem::entry(
+                    "Cut",
+                    action(|this, _, cx| this.copy_selected(TransferKind::Move, cx)),
+                )
+                .action_shortcut(&CutFiles, window)
+                .disabled(!writable),
+            ]);
+        }
+        items.push(
+            MenuItem::entry("Paste", action(|this, _, cx| this.paste(cx)))
+                .action_shortcut(&PasteFiles, window)
+                .disabled(!writable || !has_clipboard),
+        );
+        if !nodes.is_empty() {
+            items.extend([
+                MenuItem::entry(
+                    "Move to Trash",
+                    action(|this, _, cx| this.trash_selected(cx)),
+                )
+                .action_shortcut(&MoveToTrash, window)
+                .disabled(!writable),
+                MenuItem::entry(
+                    "Copy Path",
+                    action(|this, _, cx| this.copy_selected_paths(cx)),
+                ),
+                MenuItem::Separator,
+                MenuItem::entry("Properties", action(|_, _, cx| cx.emit(ShowProperties)))
+                    .action_shortcut(&ToggleProperties, window),
+                MenuItem::entry("Compress…", |window, cx| {
+                    window.dispatch_action(Compress.boxed_clone(), cx)
+                })
+                .action_shortcut(&Compress, window)
+                .disabled(!writable),
+            ]);
+        }
+        if is_archive {
+            items.extend([
+                MenuItem::entry(
+                    "Extract Here",
+                    action(|this, _, cx| this.extract_selected(false, cx)),
+                )
+                .action_shortcut(&ExtractHere, window)
+                .disabled(!writable),
+                MenuItem::entry(
+                    "Extract to Folder",
+                    action(|this, _, cx| this.extract_selected(true, cx)),
+                )
+                .action_shortcut(&ExtractToFolder, window)
+                .disabled(!writable),
+            ]);
+        }
+        items.extend([
+            MenuItem::Separator,
+            MenuItem::entry(
+                "New Folder",
+                action(|this, window, cx| this.new_item(NewItem::Folder, window, cx)),
+            )
+            .action_shortcut(&NewFolder, window)
+            .disabled(!writable),
+            MenuItem::entry(
+                if show_hidden {
+                    "Hide Hidden Files"
+                } else {
+                    "Show Hidden Files"
+                },
+                action(|this, _, cx| this.state.update(cx, |state, cx| state.toggle_hidden(cx))),
+            )
+            .action_shortcut(&ToggleHiddenFiles, window),
+        ]);
+
+        Menu::new(position, items, {
+            let entity = cx.entity();
+            move |_, cx| {
+                entity.update(cx, |this, cx| {
+                    this.context_menu = None;
+                    cx.notify();
+                })
+            }
+        })
+    }
+
+    /// Create a new item in the current directory and start renaming it.
+    pub fn new_item(&mut self, item: NewItem, window: &mut Window, cx: &mut Context<Self>) {
+        match self.state.update(cx, |state, cx| state.create(cx, &item)) {
+            Ok(Some(ix)) => self.rename(ix, window, cx),
+            Ok(None) => {}
+            Err(err) => {
+                let label = match &item {
+                    NewItem::Folder => "Creating a new folder".to_string(),
+                    NewItem::File => "Creating a new file".to_string(),
+                    NewItem::Template(template) => {
+                        format!("Creating a new file from {}", template.display())
+                    }
+                };
+                self.jobs
+                    .update(cx, |jobs, cx| jobs.report(cx, label, err.to_string()));
+            }
+        }
+    }
+
+    pub fn rename_selected(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if let Some(ix) = self.state.read(cx).selected() {
+            self.rename(ix, window, cx);
+        }
+    }
+
+    /// Start renaming the row at `ix` inline.
+    pub fn rename(&mut self, ix: usize, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(node) = self.state.read(cx).nodes().get(ix).cloned() else {
+            return;
+        };
+
+        // TODO: Handle non-utf8 file names
+        let name = node.name.to_string_lossy().to_string();
+        // Like Finder we only select the name without the extension
+        let stem_len = match name.rfind('.') {
+            Some(i) if i > 0 && node.kind != NodeKind::Directory => i,
+            _ => name.len(),
+        };
+
+        let input = cx.new(|cx| {
+            let mut input = TextInput::new(cx, name, "Name");
+            input.selected_range = 0..stem_len;
+            input
+        });
+        input.read(cx).focus_handle.focus(window);
+
+        if self.renaming.is_none() {
+            self.previous_focus = window.focused(cx);
+        }
+        self.renaming = Some((ix, input));
+        self.rename_error = None;
+        self.scroll.scroll_to_item(ix, ScrollStrategy::Top);
+        cx.notify();
+    }
+
+    fn commit_rename(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some((ix, input)) = &self.renaming else {
+            return;
+        };
+        let (ix, name) = (*ix, input.read(cx).content.to_string());
+
+        match self
+            .state
+            .update(cx, |state, cx| state.rename(cx, ix, &name))
+        {
+            Ok(()) => self.cancel_rename(window, cx),
+            Err(err) => {
+                self.rename_error = Some(err.to_string());
+                cx.notify();
+            }
         }
     }
 
+    fn cancel_rename(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.renaming = None;
+        self.rename_error = None;
+        if let Some(focus) = self.previous_focus.take() {
+            focus.focus(window);
+        }
+        cx.notify();
+    }
+
+    fn render_rename(&self, input: Entity<TextInput>, cx: &Context<Self>) -> AnyElement {
+        div()
+            .flex()
+            .flex_row()
+            .items_center()
+            .gap_1()
+            // Stop the click from reaching the row which would change the selection or open the node
+            .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+            .on_key_down(cx.listener(|this, event: &KeyDownEvent, window, cx| {
+                match &*event.keystroke.key {
+                    "enter" => this.commit_rename(window, cx),
+                    "escape" => this.cancel_rename(window, cx),
+                    _ => {}
+                }
+            }))
+            .child(
+                div()
+                    .flex_1()
+                    .border_1()
+                    .border_color(if self.rename_error.is_some() {
+                        red()
+                    } else {
+                        rgb(0x3311ff).into()
+                    })
+                    .child(input),
+            )
+            .when_some(self.rename_error.clone(), |this, err| {
+                this.child(div().text_xs().text_color(red()).child(err))
+            })
+            .into_any_element()
+    }
+
+    /// Transfer dropped paths into the directory `target`.
+    pub fn drop_into(&self, target: PathBuf) -> OnDrop {
+        let state = self.state.clone();
+        let jobs = self.jobs.clone();
+        let conflict_dialog = self.conflict_dialog.clone();
+
+        Rc::new(move |paths, kind, _, cx| {
+            // Nothing can be dropped into or dragged out of an archive
+            if state.read(cx).read_only() {
+                return;
+            }
+            let paths = paths
+                .into_iter()
+                // A directory can't be dropped into itself and moving something to where it already is does nothing
+                .filter(|path| {
+                    !target.starts_with(path)
+                        && !(kind == TransferKind::Move && path.parent() == Some(target.as_path()))
+                })
+                .collect::<Vec<_>>();
+
+            transfer(
+                cx,
+                jobs.clone(),
+                state.clone(),
+                conflict_dialog.clone(),
+                kind,
+                paths,
+                target.clone(),
+            );
+        })
+    }
+
     fn table_bounds(&self) -> Bounds<Pixels> {
         self.scroll.0.borrow().base_handle.bounds()
     }
@@ -130,6 +716,8 @@ impl DataTable {
     }
 }
 
+impl EventEmitter<ShowProperties> for DataTable {}
+
 impl Render for DataTable {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let entity = cx.entity();
@@ -149,7 +737,54 @@ impl Render for DataTable {
         })
         .detach();
 
+        let mut key_context = KeyContext::new_with_defaults();
+        key_context.add("DataTable");
+        // Keys go to the rename input or the conflict dialog instead of the list
+        if self.renaming.is_none() && !self.conflict_dialog.read(cx).is_open() {
+            key_context.add("not_editing");
+        }
+
         div()
+            .key_context(key_context)
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::select_page_up))
+            .on_action(cx.listener(Self::select_page_down))
+            .on_action(cx.listener(|this, _: &NewFolder, window, cx| {
+                this.new_item(NewItem::Folder, window, cx)
+            }))
+            .on_action(
+                cx.listener(|this, _: &CopyFiles, _, cx| {
+                    this.copy_selected(TransferKind::Copy, cx)
+                }),
+            )
+            .on_action(
+                cx.listener(|this, _: &CutFiles, _, cx| this.copy_selected(TransferKind::Move, cx)),
+            )
+            .on_action(cx.listener(|this, _: &PasteFiles, _, cx| this.paste(cx)))
+            .on_action(cx.listener(|this, _: &MoveToTrash, _, cx| this.trash_selected(cx)))
+            .on_action(cx.listener(|this, _: &ExtractHere, _, cx| this.extract_selected(false, cx)))
+            .on_action(
+                cx.listener(|this, _: &ExtractToFolder, _, cx| this.extract_selected(true, cx)),
+            )
+            // Keys which aren't bound to an action jump to the row the user is typing
+            .on_key_down(cx.listener(|this, event: &KeyDownEvent, _, cx| {
+                let modifiers = &event.keystroke.modifiers;
+                if this.renaming.is_some()
+                    || this.conflict_dialog.read(cx).is_open()
+                    || modifiers.control
+                    || modifiers.platform
+                {
+                    return;
+                }
+                if let Some(text) = event
+                    .keystroke
+                    .key_char
+                    .as_ref()
+                    .filter(|text| !text.trim().is_empty())
+                {
+                    this.type_ahead(text, cx);
+                }
+            }))
             .bg(gpui::white())
             .text_sm()
             .size_full()
@@ -164,11 +799,25 @@ impl Render for DataTable {
                     .border_1()
                     .border_color(rgb(0xE0E0E0))
                     // .rounded_sm()
-                    .child(render_titles())
+                    .child(render_titles(self.state.read(cx).searching().is_some()))
                     .child(
                         div()
                             .relative()
                             .size_full()
+                            // Files dropped from other applications onto the background are copied into the current directory
+                            .drag_over::<ExternalPaths>(|style, _, _, _| style.bg(rgb(0xF0F6FF)))
+                            .on_mouse_down(
+                                MouseButton::Right,
+                                cx.listener(|this, event: &MouseDownEvent, _, cx| {
+                                    this.deploy_context_menu(event.position, None, cx)
+                                }),
+                            )
+                            .on_drop({
+                                let on_drop = self.drop_into(self.state.read(cx).path().clone());
+                                move |paths: &ExternalPaths, window, cx| {
+                                    on_drop(paths.paths().to_vec(), TransferKind::Copy, window, cx)
+                                }
+                            })
                             .child(
                                 // TODO: Is length reactive
                                 uniform_list(entity, "items", self.state.read(cx).nodes().len(), {
@@ -179,28 +828,85 @@ impl Render for DataTable {
                                         for i in range {
                                             if let Some(node) = Some(nodes[i].clone()) {
                                                 let s = this.state.clone();
-                                                items.push(
-                                                    TableRow::new(
-                                                        i,
-                                                        node.clone(),
+                                                let rename = match &this.renaming {
+                                                    Some((ix, input)) if *ix == i => {
+                                                        Some(this.render_rename(input.clone(), cx))
+                                                    }
+                                                    _ => None,
+                                                };
+                                                let selected = s.read(cx).is_selected(i);
+                                                // Dragging a selected row drags the whole selection
+                                                let drag = DraggedNodes {
+                                                    paths: if selected {
                                                         s.read(cx)
-                                                            .selected()
-                                                            .map(|s| s == i)
-                                                            .unwrap_or(false),
-                                                    )
-                                                    .on_click(move |event, _, cx| {
-                                                        if event.down.click_count == 1 {
-                                                            s.update(cx, |s, cx| {
-                                                                s.set_selection(cx, i)
-                                                            });
-                                                        } else {
-                                                            let modifier =
-                                                                event.down.modifiers.platform
+                                                            .selected_nodes()
+                                                            .iter()
+                                                            .map(|node| node.path.clone())
+                                                            .collect()
+                                                    } else {
+                                                        vec![node.path.clone()]
+                                                    },
+                                                };
+                                                let on_drop = (node.kind == NodeKind::Directory)
+                                                    .then(|| this.drop_into(node.path.clone()));
+                                                let relative_path = s
+                                                    .read(cx)
+                                                    .relative_path(&node)
+                                                    .map(|path| path.to_string_lossy().to_string());
+                                                items.push(
+                                                    TableRow::new(i, node.clone(), selected)
+                                                        .highlights(
+                                                            s.read(cx).highlights(i).to_vec(),
+                                                        )
+                                                        .on_drag(drag)
+                                                        .when_some(relative_path, |this, path| {
+                                                            this.relative_path(path)
+                                                        })
+                                                        .on_click(move |event, _, cx| {
+                                                            if event.down.click_count == 1 {
+                                                                let modifiers =
+                                                                    event.down.modifiers;
+                                                                s.update(cx, |s, cx| {
+                                                                    if modifiers.shift {
+                                                                        s.extend_selection(cx, i)
+                                                                    } else if modifiers.platform
+                                                                        || modifiers.control
+                                                                    {
+                                                                        s.toggle_selection(cx, i)
+                                                                    } else {
+                                                                        s.set_selection(cx, i)
+                                                                    }
+                                                                });
+                                                            } else {
+                                                                let modifier = event
+                                                                    .down
+                                                                    .modifiers
+                                                                    .platform
                                                                     || event.down.modifiers.shift; // TODO: Make this better
 
-                                                            open_node(&s, cx, &node, modifier);
-                                                        }
-                                                    }),
+                                                                open_node(&s, cx, &node, modifier);
+                                                            }
+                                                        })
+                                                        .when_some(rename, |this, rename| {
+                                                            this.rename(rename)
+                                                        })
+                                                        .when_some(on_drop, |this, on_drop| {
+                                                            this.on_drop(on_drop)
+                                                        })
+                                                        .on_right_click({
+                                                            let entity = cx.entity();
+                                                            move |event, _, cx| {
+                                                                // Don't open the background menu as well
+                                                                cx.stop_propagation();
+                                                                entity.update(cx, |this, cx| {
+                                                                    this.deploy_context_menu(
+                                                                        event.position,
+                                                                        Some(i),
+                                                                        cx,
+                                                                    )
+                                                                });
+                                                            }
+                                                        }),
                                                 );
                                             }
                                         }
@@ -211,8 +917,22 @@ impl Render for DataTable {
                                 .size_full()
                                 .track_scroll(self.scroll.clone()),
                             )
-                            .child(self.render_scrollbar(window, cx)),
+                            .child(self.render_scrollbar(window, cx))
+                            .when(self.state.read(cx).loading(), |this| {
+                                this.child(
+                                    div()
+                                        .absolute()
+                                        .top_0()
+                                        .left_0()
+                                        .p_2()
+                                        .text_color(rgb(0x555555))
+                                        .child("Reading archive…"),
+                                )
+                            }),
                     ),
             )
+            .when_some(self.context_menu, |this, position| {
+                this.child(self.render_context_menu(position, window, cx))
+            })
     }
 }
@@ -0,0 +1,240 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use gpui::*;
+use ignore::WalkBuilder;
+use opener::open;
+
+use crate::{fuzzy::fuzzy_match, jobs::Jobs, state::State};
+
+use super::{Picker, PickerDelegate, PickerItem};
+
+/// Only the best matches are shown, ranking more isn't useful.
+const MAX_MATCHES: usize = 100;
+/// Stop walking huge trees, eg. a home directory, after this many files.
+const MAX_CANDIDATES: usize = 500_000;
+
+struct FileMatch {
+    /// Relative to the root.
+    path: Arc<str>,
+    positions: Vec<usize>,
+}
+
+/// Finds files anywhere below the current directory by fuzzy matching their relative paths.
+pub struct FileFinder {
+    state: Entity<State>,
+    jobs: Entity<Jobs>,
+    root: PathBuf,
+    /// The files below each root the finder has been opened in, and whether hidden files were included.
+    // TODO: Invalidate when files are created or deleted
+    candidates: HashMap<(PathBuf, bool), Arc<Vec<Arc<str>>>>,
+    matches: Vec<FileMatch>,
+    /// The latest query, which is matched once a running walk is done.
+    query: String,
+    /// Only one walk runs at a time, however fast the user types.
+    walk: Option<Walk>,
+    /// Set when the finder was opened inside an archive, where there's nothing to walk.
+    in_archive: bool,
+    _task: Option<Task<()>>,
+}
+
+/// A walk collecting the candidates for a root, see [`FileFinder::start_walk`].
+struct Walk {
+    key: (PathBuf, bool),
+    cancelled: Arc<AtomicBool>,
+    _task: Task<()>,
+}
+
+impl FileFinder {
+    pub fn new(state: Entity<State>, jobs: Entity<Jobs>) -> Self {
+        Self {
+            state,
+            jobs,
+            root: PathBuf::new(),
+            candidates: HashMap::new(),
+            matches: Vec::new(),
+            query: String::new(),
+            walk: None,
+            in_archive: false,
+            _task: None,
+        }
+    }
+
+    /// Walk the root in `key` in the background, caching the files and matching them against
+    /// the latest query once it's done.
+    fn start_walk(&mut self, key: (PathBuf, bool), cx: &mut Context<Picker<Self>>) {
+        self.cancel_walk();
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let executor = cx.background_executor().clone();
+        let _task = cx.spawn({
+            let (key, cancelled) = (key.clone(), cancelled.clone());
+            async move |this, cx| {
+                let (root, show_hidden) = key.clone();
+                let candidates = executor
+                    .spawn({
+                        let cancelled = cancelled.clone();
+                        async move { walk(root, show_hidden, &cancelled) }
+                    })
+                    .await;
+                // A partial walk would miss files, so it isn't kept
+                if cancelled.load(Ordering::Relaxed) {
+                    return;
+                }
+
+                this.update(cx, |this, cx| {
+                    this.delegate.walk = None;
+                    this.delegate.candidates.insert(key, Arc::new(candidates));
+                    let query = this.delegate.query.clone();
+                    this.delegate.update_matches(query, cx);
+                })
+                .ok();
+            }
+        });
+        self.walk = Some(Walk {
+            key,
+            cancelled,
+            _task,
+        });
+    }
+
+    /// Stop the running walk, eg. because the finder was closed or opened in another directory.
+    fn cancel_walk(&mut self) {
+        if let Some(walk) = self.walk.take() {
+            walk.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Every file below `root`, respecting `.gitignore` and friends. Stops early once `cancelled` is set.
+fn walk(root: PathBuf, show_hidden: bool, cancelled: &AtomicBool) -> Vec<Arc<str>> {
+    WalkBuilder::new(&root)
+        .hidden(!show_hidden)
+        .build()
+        .take_while(|_| !cancelled.load(Ordering::Relaxed))
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                // eg. a directory which can't be read, the rest of the files can still be found
+                log::warn!("Failed to walk {}: {err}", root.display());
+                None
+            }
+        })
+        .filter(|entry| entry.file_type().is_some_and(|t| !t.is_dir()))
+        .filter_map(|entry| {
+            let path = entry.path().strip_prefix(&root).ok()?;
+            // TODO: Handle non-utf8 file names
+            Some(path.to_string_lossy().into())
+        })
+        .take(MAX_CANDIDATES)
+        .collect()
+}
+
+impl PickerDelegate for FileFinder {
+    fn placeholder(&self) -> &'static str {
+        "Find a file"
+    }
+
+    fn update_matches(&mut self, query: String, cx: &mut Context<Picker<Self>>) {
+        let state = self.state.read(cx);
+        // The walk only sees real directories
+        self.in_archive = state.read_only();
+        if self.in_archive {
+            self.cancel_walk();
+            self.matches.clear();
+            self._task = None;
+            return;
+        }
+        let key = (state.path().clone(), state.show_hidden());
+        self.root = key.0.clone();
+        self.query = query.clone();
+
+        let Some(candidates) = self.candidates.get(&key).cloned() else {
+            // The matches are updated once the walk is done, with whatever the query is by then
+            self.matches.clear();
+            self._task = None;
+            if !matches!(&self.walk, Some(walk) if walk.key == key) {
+                self.start_walk(key, cx);
+            }
+            return;
+        };
+
+        let executor = cx.background_executor().clone();
+        self._task = Some(cx.spawn(async move |this, cx| {
+            let matches = executor
+                .spawn(async move {
+                    let mut matches = candidates
+                        .iter()
+                        .filter_map(|path| {
+                            let (score, positions) = fuzzy_match(path, &query)?;
+                            Some((
+                                score,
+                                FileMatch {
+                                    path: path.clone(),
+                                    positions,
+                                },
+                            ))
+                        })
+                        .collect::<Vec<_>>();
+                    // Stable so equally good matches keep the walk's order
+                    matches.sort_by(|(a, _), (b, _)| b.cmp(a));
+                    matches.truncate(MAX_MATCHES);
+                    matches.into_iter().map(|(_, m)| m).collect::<Vec<_>>()
+                })
+                .await;
+
+            this.update(cx, |this, cx| {
+                this.delegate.matches = matches;
+                this.matches_updated(cx);
+            })
+            .ok();
+        }));
+    }
+
+    fn dismissed(&mut self) {
+        self.cancel_walk();
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn notice(&self) -> Option<SharedString> {
+        self.in_archive
+            .then(|| "Files can't be found inside archives".into())
+    }
+
+    fn render_match(&self, ix: usize) -> PickerItem {
+        let m = &self.matches[ix];
+        PickerItem {
+            label: m.path.to_string().into(),
+            detail: None,
+            highlights: m.positions.clone(),
+        }
+    }
+
+    fn confirm(
+        &mut self,
+        ix: usize,
+        secondary: bool,
+        _: &mut Window,
+        cx: &mut Context<Picker<Self>>,
+    ) {
+        let path = self.root.join(&*self.matches[ix].path);
+        if secondary {
+            if let Err(err) = open(&path) {
+                let label = format!("Opening {}", path.display());
+                self.jobs
+                    .update(cx, |jobs, cx| jobs.report(cx, label, err.to_string()));
+            }
+        } else {
+            self.state.update(cx, |state, cx| state.reveal(cx, path));
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::{
+    config::SmartFolder,
+    filter::FilterMode,
+    query::Query,
+    state::{PathChange, State},
+};
+
+use super::{OnChange, TextInput, button};
+
+/// Emitted when the user wants to keep the current search as a smart folder.
+pub struct SaveSearch(pub SmartFolder);
+
+/// Narrows the listing down to the names matching a pattern.
+///
+/// In recursive mode it searches every directory below the current one instead.
+pub struct FilterBar {
+    state: Entity<State>,
+    input: Entity<TextInput>,
+    mode: FilterMode,
+    recursive: bool,
+    /// Why the query couldn't be parsed.
+    error: Option<String>,
+    open: bool,
+    /// Where focus should go back to when the filter is closed.
+    previous_focus: Option<FocusHandle>,
+}
+
+impl FilterBar {
+    pub fn init(cx: &mut Context<Self>, state: Entity<State>) -> Self {
+        let input = cx
+            .new(|cx| TextInput::new(cx, "", "Filter, eg. report ext:pdf size:>1MB modified:<7d"));
+        cx.subscribe(&input, |this, _, _: &OnChange, cx| this.update_filter(cx))
+            .detach();
+
+        // `State` clears the filter itself when the path changes
+        cx.subscribe(&state, |this, state, _: &PathChange, cx| {
+            // A smart folder's search is shown so it can be refined or cancelled
+            let smart_folder = state.read(cx).smart_folder().cloned();
+            this.open = smart_folder.is_some();
+            this.recursive = smart_folder.is_some();
+            this.error = None;
+            let query = match smart_folder {
+                Some(smart_folder) => {
+                    this.mode = smart_folder.mode;
+                    smart_folder.query
+                }
+                None => String::new(),
+            };
+            this.input
+                .update(cx, |input, cx| input.set_content(query, cx));
+            cx.notify();
+        })
+        .detach();
+
+        Self {
+            state,
+            input,
+            mode: FilterMode::default(),
+            recursive: false,
+            error: None,
+            open: false,
+            previous_focus: None,
+        }
+    }
+
+    /// Open the filter, optionally starting with some text the user has already typed.
+    pub fn open(
+        &mut self,
+        recursive: bool,
+        text: Option<&str>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if !self.open {
+            self.previous_focus = window.focused(cx);
+        }
+        self.open = true;
+        self.recursive = recursive;
+
+        if let Some(text) = text {
+            let content = format!("{}{text}", self.input.read(cx).content);
+            self.input
+                .update(cx, |input, cx| input.set_content(content, cx));
+        }
+        self.input.read(cx).focus_handle.focus(window);
+        self.update_filter(cx);
+    }
+
+    pub fn close(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.open = false;
+        self.error = None;
+        self.input.update(cx, |input, cx| input.set_content("", cx));
+        self.state.update(cx, |state, cx| {
+            if state.searching().is_some() {
+                state.set_search(cx, None);
+            }
+            state.set_filter(cx, None)
+        });
+        self.restore_focus(window);
+        cx.notify();
+    }
+
+    fn restore_focus(&mut self, window: &mut Window) {
+        if let Some(focus) = self.previous_focus.take() {
+            focus.focus(window);
+        }
+    }
+
+    fn update_filter(&mut self, cx: &mut Context<Self>) {
+        let filter = match Query::parse(&self.input.read(cx).content, self.mode) {
+            Ok(query) => query,
+            Err(err) => {
+                // Keep showing the last valid results while the user fixes it
+                self.input.update(cx, |input, cx| {
+                    input.set_error_range(Some(err.range.clone()), cx)
+                });
+                self.error = Some(err.message);
+                cx.notify();
+                return;
+            }
+        };
+        self.input
+            .update(cx, |input, cx| input.set_error_range(None, cx));
+        self.error = None;
+
+        // The walk only sees real directories, filtering the archive's folder still works
+        if self.recursive && self.state.read(cx).read_only() {
+            self.error = Some("Subfolders can't be searched inside archives".to_string());
+            cx.notify();
+            return;
+        }
+
+        let recursive = self.recursive;
+        self.state.update(cx, |state, cx| {
+            if recursive {
+                state.set_filter(cx, None);
+                state.set_search(cx, Some(filter));
+            } else {
+                if state.searching().is_some() {
+                    state.set_search(cx, None);
+                }
+                state.set_filter(cx, Some(filter));
+            }
+        });
+    }
+}
+
+impl EventEmitter<SaveSearch> for FilterBar {}
+
+impl Render for FilterBar {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !self.open {
+            return div();
+        }
+
+        let state = self.state.read(cx);
+        let count = state.nodes().len();
+        let searching = state.searching().is_some_and(|search| !search.finished);
+        let smart_folder = state
+            .smart_folder()
+            .map(|smart_folder| smart_folder.name.clone());
+        let title = match &smart_folder {
+            Some(name) => name.clone(),
+            None if self.recursive => "Search".to_string(),
+            None => "Filter".to_string(),
+        };
+
+        div()
+            .flex()
+            .flex_row()
+            .items_center()
+            .gap_2()
+            .px_2()
+            .py_1()
+            .bg(rgb(0xF0F0F0))
+            .text_sm()
+            .on_key_down(cx.listener(|this, event: &KeyDownEvent, window, cx| {
+                match &*event.keystroke.key {
+                    "escape" => this.close(window, cx),
+                    // Keep the filter and go back to the list
+                    "enter" => {
+                        this.restore_focus(window);
+                        cx.notify();
+                    }
+                    "up" => this.state.update(cx, |state, cx| state.back_selected(cx)),
+                    "down" => this.state.update(cx, |state, cx| state.next_selected(cx)),
+                    _ => {}
+                }
+            }))
+            .child(title)
+            .child(
+                div()
+                    .flex_1()
+                    .bg(white())
+                    .border_1()
+                    .border_color(rgb(0x3311ff))
+                    .child(self.input.clone()),
+            )
+            .child(button(self.mode.label(), {
+                let entity = cx.entity();
+                move |_, cx| {
+                    entity.update(cx, |this, cx| {
+                        this.mode = this.mode.next();
+                        this.update_filter(cx);
+                    })
+                }
+            }))
+            .child(button(
+                if self.recursive {
+                    "Subfolders"
+                } else {
+                    "This Folder"
+                },
+                {
+                    let entity = cx.entity();
+                    move |_, cx| {
+                        entity.update(cx, |this, cx| {
+                            this.recursive = !this.recursive;
+                            this.update_filter(cx);
+                        })
+                    }
+                },
+            ))
+            .when_some(self.error.clone(), |this, err| {
+                this.child(div().text_xs().text_color(red()).child(err))
+            })
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(rgb(0x555555))
+                    .child(if searching {
+                        format!("Searching… {count} matches")
+                    } else {
+                        format!("{count} matches")
+                    }),
+            )
+            .when(
+                // A smart folder is already saved
+                self.recursive
+                    && smart_folder.is_none()
+                    && self.error.is_none()
+                    && !self.input.read(cx).content.is_empty(),
+                |this| {
+                    this.child(button("Save", {
+                        let entity = cx.entity();
+                        move |_, cx| {
+                            entity.update(cx, |this, cx| {
+                                let query = this.input.read(cx).content.to_string();
+                                cx.emit(SaveSearch(SmartFolder {
+                                    name: query.clone(),
+                                    root: this.state.read(cx).path().clone(),
+                                    query,
+                                    mode: this.mode,
+                                }))
+                            })
+                        }
+                    }))
+                },
+            )
+            .when(searching, |this| {
+                this.child(button("Stop", {
+                    let state = self.state.clone();
+                    move |_, cx| state.update(cx, |state, _| state.cancel_search())
+                }))
+            })
+            .child(button("Close", {
+                let entity = cx.entity();
+                move |window, cx| entity.update(cx, |this, cx| this.close(window, cx))
+            }))
+    }
+}
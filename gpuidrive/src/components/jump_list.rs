@@ -0,0 +1,178 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use gpui::*;
+use opener::open;
+
+use crate::{frecency, fuzzy::fuzzy_match, jobs::Jobs, state::State};
+
+use super::{Picker, PickerDelegate, PickerItem};
+
+/// Only the best matches are shown.
+const MAX_MATCHES: usize = 100;
+
+struct Candidate {
+    path: PathBuf,
+    /// The path with the home directory shortened to `~`, which is what's matched against.
+    label: String,
+}
+
+struct Jump {
+    path: PathBuf,
+    /// The path with the home directory shortened to `~`, which is what's matched against.
+    label: SharedString,
+    positions: Vec<usize>,
+}
+
+/// Jumps to a previously visited directory, best ranked by how often and how recently it was visited.
+pub struct JumpList {
+    state: Entity<State>,
+    jobs: Entity<Jobs>,
+    /// Every visited directory, loaded once each time the jump list opens.
+    candidates: Option<Arc<Vec<Candidate>>>,
+    loading: Option<Task<()>>,
+    query: String,
+    matches: Vec<Jump>,
+    _task: Option<Task<()>>,
+}
+
+impl JumpList {
+    pub fn new(state: Entity<State>, jobs: Entity<Jobs>) -> Self {
+        Self {
+            state,
+            jobs,
+            candidates: None,
+            loading: None,
+            query: String::new(),
+            matches: Vec::new(),
+            _task: None,
+        }
+    }
+
+    fn load_candidates(&mut self, cx: &mut Context<Picker<Self>>) {
+        let task = cx.background_executor().spawn(async move { load() });
+        self.loading = Some(cx.spawn(async move |this, cx| {
+            let candidates = task.await;
+            this.update(cx, |this, cx| {
+                this.delegate.candidates = Some(Arc::new(candidates));
+                this.delegate.loading = None;
+                let query = this.delegate.query.clone();
+                this.delegate.update_matches(query, cx);
+            })
+            .ok();
+        }));
+    }
+}
+
+fn label(path: &Path, home: Option<&Path>) -> String {
+    // TODO: Handle non-utf8 paths
+    match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".into(),
+        Some(rest) => format!("~/{}", rest.to_string_lossy()),
+        None => path.to_string_lossy().to_string(),
+    }
+}
+
+/// Every visited directory which still exists, best first.
+fn load() -> Vec<Candidate> {
+    let home = dirs::home_dir();
+    frecency::load()
+        .into_iter()
+        .map(|entry| Candidate {
+            label: label(&entry.path, home.as_deref()),
+            path: entry.path,
+        })
+        .collect()
+}
+
+fn find(candidates: &[Candidate], query: &str) -> Vec<Jump> {
+    candidates
+        .iter()
+        .filter_map(|candidate| {
+            let label = &candidate.label;
+            let (_, positions) = fuzzy_match(label, query)?;
+            // Like zoxide the end of the query has to match the directory's own name, so
+            // "proj api" goes to `~/work/projects/api` rather than `~/work/projects/api/docs`
+            let name = label.rfind('/').map_or(0, |ix| ix + 1);
+            if positions.last().is_some_and(|&last| last < name) {
+                return None;
+            }
+            Some(Jump {
+                path: candidate.path.clone(),
+                label: label.clone().into(),
+                positions,
+            })
+        })
+        // Already ordered by frecency
+        .take(MAX_MATCHES)
+        .collect()
+}
+
+impl PickerDelegate for JumpList {
+    fn placeholder(&self) -> &'static str {
+        "Jump to a folder, eg. proj api"
+    }
+
+    fn update_matches(&mut self, query: String, cx: &mut Context<Picker<Self>>) {
+        self.query = query.clone();
+        let Some(candidates) = self.candidates.clone() else {
+            // The matches are updated once loaded, with whatever the query is by then
+            if self.loading.is_none() {
+                self.load_candidates(cx);
+            }
+            return;
+        };
+
+        let task = cx
+            .background_executor()
+            .spawn(async move { find(&candidates, &query) });
+        self._task = Some(cx.spawn(async move |this, cx| {
+            let matches = task.await;
+            this.update(cx, |this, cx| {
+                this.delegate.matches = matches;
+                this.matches_updated(cx);
+            })
+            .ok();
+        }));
+    }
+
+    fn dismissed(&mut self) {
+        // Load again next time so new visits show up
+        self.candidates = None;
+        self.loading = None;
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn render_match(&self, ix: usize) -> PickerItem {
+        let jump = &self.matches[ix];
+        PickerItem {
+            label: jump.label.clone(),
+            detail: None,
+            highlights: jump.positions.clone(),
+        }
+    }
+
+    fn confirm(
+        &mut self,
+        ix: usize,
+        secondary: bool,
+        _: &mut Window,
+        cx: &mut Context<Picker<Self>>,
+    ) {
+        let path = self.matches[ix].path.clone();
+        if secondary {
+            if let Err(err) = open(&path) {
+                let label = format!("Opening {}", path.display());
+                self.jobs
+                    .update(cx, |jobs, cx| jobs.report(cx, label, err.to_string()));
+            }
+        } else {
+            self.state.update(cx, |state, cx| state.set_path(cx, path));
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::rc::Rc;
+
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::keymap;
+
+/// A single entry in a [`Menu`].
+#[derive(Clone)]
+pub enum MenuItem {
+    Entry {
+        label: SharedString,
+        shortcut: Option<SharedString>,
+        disabled: bool,
+        handler: Rc<dyn Fn(&mut Window, &mut App)>,
+    },
+    Submenu {
+        label: SharedString,
+        items: Vec<MenuItem>,
+    },
+    Separator,
+}
+
+impl MenuItem {
+    pub fn entry(
+        label: impl Into<SharedString>,
+        handler: impl Fn(&mut Window, &mut App) + 'static,
+    ) -> Self {
+        Self::Entry {
+            label: label.into(),
+            shortcut: None,
+            disabled: false,
+            handler: Rc::new(handler),
+        }
+    }
+
+    pub fn submenu(label: impl Into<SharedString>, items: Vec<MenuItem>) -> Self {
+        Self::Submenu {
+            label: label.into(),
+            items,
+        }
+    }
+
+    pub fn shortcut(mut self, shortcut: impl Into<SharedString>) -> Self {
+        if let Self::Entry { shortcut: s, .. } = &mut self {
+            *s = Some(shortcut.into());
+        }
+        self
+    }
+
+    /// Show the keystrokes currently bound to `action`, if there are any.
+    pub fn action_shortcut(self, action: &dyn Action, window: &Window) -> Self {
+        match keymap::shortcut(action, window) {
+            Some(shortcut) => self.shortcut(shortcut),
+            None => self,
+        }
+    }
+
+    pub fn disabled(mut self, disabled: bool) -> Self {
+        if let Self::Entry { disabled: d, .. } = &mut self {
+            *d = disabled;
+        }
+        self
+    }
+}
+
+/// A popup menu anchored at a position in the window.
+///
+/// The owner is responsible for tracking if the menu is open, `on_dismiss` is called
+/// when an entry is clicked or the user clicks outside of the menu.
+#[derive(IntoElement)]
+pub struct Menu {
+    position: Point<Pixels>,
+    items: Vec<MenuItem>,
+    on_dismiss: Rc<dyn Fn(&mut Window, &mut App)>,
+}
+
+impl Menu {
+    pub fn new(
+        position: Point<Pixels>,
+        items: Vec<MenuItem>,
+        on_dismiss: impl Fn(&mut Window, &mut App) + 'static,
+    ) -> Self {
+        Self {
+            position,
+            items,
+            on_dismiss: Rc::new(on_dismiss),
+        }
+    }
+}
+
+fn render_items(items: Vec<MenuItem>, on_dismiss: Rc<dyn Fn(&mut Window, &mut App)>) -> Div {
+    div()
+        .flex()
+        .flex_col()
+        .min_w(px(180.))
+        .py_1()
+        .bg(white())
+        .text_sm()
+        .text_color(black())
+        .border_1()
+        .border_color(rgb(0xE0E0E0))
+        .rounded_md()
+        .shadow_md()
+        // Stop clicks in the menu from reaching the backdrop
+        .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+        .on_mouse_down(MouseButton::Right, |_, _, cx| cx.stop_propagation())
+        .children(items.into_iter().map(|item| {
+            match item {
+                MenuItem::Entry {
+                    label,
+                    shortcut,
+                    disabled,
+                    handler,
+                } => {
+                    div()
+                        .id(label.clone())
+                        .flex()
+                        .flex_row()
+                        .justify_between()
+                        .gap_4()
+                        .px_2()
+                        .py_0p5()
+                        .child(label)
+                        .children(shortcut.map(|shortcut| {
+                            div().text_xs().text_color(rgb(0x999999)).child(shortcut)
+                        }))
+                        .map(|this| {
+                            if disabled {
+                                this.opacity(0.5).cursor_not_allowed()
+                            } else {
+                                let on_dismiss = on_dismiss.clone();
+                                this.cursor_pointer()
+                                    .hover(|this| this.bg(rgb(0xE8E8E8)))
+                                    .on_click(move |_, window, cx| {
+                                        on_dismiss(window, cx);
+                                        handler(window, cx);
+                                    })
+                            }
+                        })
+                        .into_any_element()
+                }
+                MenuItem::Submenu { label, items } => {
+                    let group = SharedString::from(format!("submenu-{label}"));
+                    div()
+                        .group(group.clone())
+                        .relative()
+                        .child(
+                            div()
+                                .flex()
+                                .flex_row()
+                                .justify_between()
+                                .px_2()
+                                .py_0p5()
+                                .hover(|this| this.bg(rgb(0xE8E8E8)))
+                                .when(items.is_empty(), |this| this.opacity(0.5))
+                                .child(label)
+                                .child("▸"),
+                        )
+                        .when(!items.is_empty(), |this| {
+                            this.child(
+                                div()
+                                    .absolute()
+                                    .top_0()
+                                    .left(relative(1.))
+                                    .invisible()
+                                    .group_hover(group, |this| this.visible())
+                                    .child(render_items(items, on_dismiss.clone())),
+                            )
+                        })
+                        .into_any_element()
+                }
+                MenuItem::Separator => div().my_1().h(px(1.)).bg(rgb(0xE0E0E0)).into_any_element(),
+            }
+        }))
+}
+
+impl RenderOnce for Menu {
+    fn render(self, window: &mut Window, _: &mut App) -> impl IntoElement {
+        let viewport = window.viewport_size();
+        let on_dismiss = self.on_dismiss.clone();
+
+        // The backdrop catches clicks outside of the menu. We can't use `on_mouse_down_out`
+        // as submenus are rendered outside of the bounds of their parent.
+        deferred(
+            anchored().position(point(px(0.), px(0.))).child(
+                div()
+                    .w(viewport.width)
+                    .h(viewport.height)
+                    .occlude()
+                    .on_mouse_down(MouseButton::Left, {
+                        let on_dismiss = on_dismiss.clone();
+                        move |_, window, cx| on_dismiss(window, cx)
+                    })
+                    .on_mouse_down(MouseButton::Right, move |_, window, cx| {
+                        on_dismiss(window, cx)
+                    })
+                    .child(
+                        anchored()
+                            .position(self.position)
+                            .snap_to_window()
+                            .child(render_items(self.items, self.on_dismiss)),
+                    ),
+            ),
+        )
+        .with_priority(1)
+    }
+}
@@ -1,35 +1,44 @@
 use std::path::PathBuf;
 
-use gpui::*;
+use gpui::{prelude::FluentBuilder, *};
 
 use crate::{
+    actions::NewFolder,
     components::TextInput,
+    ops::{self, NewItem},
     state::{PathChange, State},
 };
 
-use super::{Icon, OnChange, button, button2};
+use super::{DataTable, Icon, Menu, MenuItem, OnChange, button, button2, drop_target};
 
 pub struct PathBar {
     state: Entity<State>,
+    data_table: Entity<DataTable>,
     text_input: Entity<TextInput>,
+    /// The position of the "New" menu and the templates to show in it, if it's open.
+    new_menu: Option<(Point<Pixels>, Vec<PathBuf>)>,
 }
 
 impl PathBar {
-    pub fn init(cx: &mut Context<Self>, state: Entity<State>) -> Self {
+    pub fn init(
+        cx: &mut Context<Self>,
+        state: Entity<State>,
+        data_table: Entity<DataTable>,
+    ) -> Self {
         let text_input = cx.new(|cx: &mut Context<TextInput>| {
             cx.subscribe(&state, |subscriber, emitter, event: &PathChange, cx| {
-                subscriber.content =
-                    SharedString::new(emitter.read(cx).path().to_str().unwrap().to_string()); // TODO: Utf-8 strings
+                subscriber.content = emitter.read(cx).display_path().into();
             })
             .detach();
 
             TextInput {
                 focus_handle: cx.focus_handle(),
-                content: SharedString::new(state.read(cx).path().to_str().unwrap().to_string()), // TODO: Utf-8 strings
+                content: state.read(cx).display_path().into(),
                 placeholder: "Type here...".into(),
                 selected_range: 0..0,
                 selection_reversed: false,
                 marked_range: None,
+                error_range: None,
                 last_layout: None,
                 last_bounds: None,
                 is_selecting: false,
@@ -48,7 +57,109 @@ impl PathBar {
         })
         .detach();
 
-        Self { state, text_input }
+        Self {
+            state,
+            data_table,
+            text_input,
+            new_menu: None,
+        }
+    }
+
+    fn render_new_menu(
+        &self,
+        position: Point<Pixels>,
+        templates: &[PathBuf],
+        window: &Window,
+        cx: &mut Context<Self>,
+    ) -> Menu {
+        let new_item = |item: NewItem| {
+            let data_table = self.data_table.clone();
+            move |window: &mut Window, cx: &mut App| {
+                data_table.update(cx, |data_table, cx| {
+                    data_table.new_item(item.clone(), window, cx)
+                })
+            }
+        };
+
+        Menu::new(
+            position,
+            vec![
+                MenuItem::entry("New Folder", new_item(NewItem::Folder))
+                    .action_shortcut(&NewFolder, window),
+                MenuItem::entry("New File", new_item(NewItem::File)),
+                MenuItem::Separator,
+                MenuItem::submenu(
+                    "New from Template",
+                    templates
+                        .iter()
+                        .map(|template| {
+                            MenuItem::entry(
+                                template
+                                    .file_name()
+                                    .unwrap_or_default()
+                                    .to_string_lossy()
+                                    .to_string(),
+                                new_item(NewItem::Template(template.clone())),
+                            )
+                        })
+                        .collect(),
+                ),
+            ],
+            {
+                let entity = cx.entity();
+                move |_, cx| {
+                    entity.update(cx, |this, cx| {
+                        this.new_menu = None;
+                        cx.notify();
+                    })
+                }
+            },
+        )
+    }
+
+    /// A segment for each directory in the current path, which can be clicked to go there or dropped onto.
+    fn render_breadcrumbs(&self, cx: &App) -> Div {
+        let path = self.state.read(cx).path();
+        let mut ancestors = path.ancestors().collect::<Vec<_>>();
+        ancestors.reverse();
+
+        div()
+            .flex()
+            .flex_row()
+            .flex_wrap()
+            .items_center()
+            .px_1()
+            .text_xs()
+            .text_color(rgb(0x555555))
+            .children(ancestors.into_iter().enumerate().map(|(ix, dir)| {
+                let label = match dir.file_name() {
+                    // TODO: Handle non-utf8 paths
+                    Some(name) => name.to_string_lossy().to_string(),
+                    None => dir.display().to_string(),
+                };
+                let on_drop = self.data_table.read(cx).drop_into(dir.to_path_buf());
+
+                div()
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .when(ix > 0, |this| this.child("›"))
+                    .child(
+                        drop_target(div().id(("breadcrumb", ix)), on_drop)
+                            .px_1()
+                            .rounded_sm()
+                            .cursor_pointer()
+                            .hover(|this| this.bg(rgb(0xE0E0E0)))
+                            .on_click({
+                                let state = self.state.clone();
+                                let dir = dir.to_path_buf();
+                                move |_, _, cx| {
+                                    state.update(cx, |state, cx| state.set_path(cx, dir.clone()))
+                                }
+                            })
+                            .child(label),
+                    )
+            }))
     }
 }
 
@@ -56,28 +167,47 @@ impl Render for PathBar {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         div()
             .flex()
+            .flex_col()
             .bg(rgb(0xffffff))
             .text_color(rgb(0x0))
-            .child(div().w_full().child(self.text_input.clone()))
-            .child(button2("Up", !self.state.read(cx).can_go_up(), {
-                let state = self.state.clone();
-                move |_, cx| {
-                    state.update(cx, |state, cx| state.go_up(cx));
-                }
-            }))
-            .child(button2("Back", !self.state.read(cx).can_go_back(), {
-                let state = self.state.clone();
-                move |_, cx| {
-                    state.update(cx, |state, cx| state.go_back(cx));
-                }
-            }))
-            .child(button2("Forward", !self.state.read(cx).can_go_forward(), {
-                let state = self.state.clone();
-                move |_, cx| {
-                    state.update(cx, |state, cx| state.go_forward(cx));
-                }
-            }))
-            .child(Icon::PhFile)
+            .child(
+                div()
+                    .flex()
+                    .child(div().w_full().child(self.text_input.clone()))
+                    .child(button("New", {
+                        let entity = cx.entity();
+                        move |window, cx| {
+                            let position = window.mouse_position();
+                            entity.update(cx, |this, cx| {
+                                this.new_menu = Some((position, ops::templates()));
+                                cx.notify();
+                            })
+                        }
+                    }))
+                    .child(button2("Up", !self.state.read(cx).can_go_up(), {
+                        let state = self.state.clone();
+                        move |_, cx| {
+                            state.update(cx, |state, cx| state.go_up(cx));
+                        }
+                    }))
+                    .child(button2("Back", !self.state.read(cx).can_go_back(), {
+                        let state = self.state.clone();
+                        move |_, cx| {
+                            state.update(cx, |state, cx| state.go_back(cx));
+                        }
+                    }))
+                    .child(button2("Forward", !self.state.read(cx).can_go_forward(), {
+                        let state = self.state.clone();
+                        move |_, cx| {
+                            state.update(cx, |state, cx| state.go_forward(cx));
+                        }
+                    }))
+                    .child(Icon::PhFile),
+            )
+            .child(self.render_breadcrumbs(cx))
+            .when_some(self.new_menu.clone(), |this, (position, templates)| {
+                this.child(self.render_new_menu(position, &templates, window, cx))
+            })
         // svg()
         //     .path("./gpuidrive/icons/PhFile.svg")
         //     .size_8()
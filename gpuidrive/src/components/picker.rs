@@ -0,0 +1,254 @@
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::actions::{Confirm, Dismiss, SecondaryConfirm, SelectNext, SelectPrevious};
+
+use super::{OnChange, TextInput};
+
+/// What a picker lists and what happens when one of its matches is chosen.
+pub trait PickerDelegate: Sized + 'static {
+    fn placeholder(&self) -> &'static str;
+
+    /// Called when the picker opens and whenever the query changes. If the matches are found
+    /// in the background call [`Picker::matches_updated`] once they're ready.
+    fn update_matches(&mut self, query: String, cx: &mut Context<Picker<Self>>);
+
+    fn match_count(&self) -> usize;
+
+    /// Shown instead of the matches while there are none, eg. why nothing can be found.
+    fn notice(&self) -> Option<SharedString> {
+        None
+    }
+
+    fn render_match(&self, ix: usize) -> PickerItem;
+
+    /// Called when the picker is closed, eg. to stop background work.
+    fn dismissed(&mut self) {}
+
+    /// `secondary` is set when the match was chosen with ctrl-enter (cmd-enter on macOS).
+    fn confirm(
+        &mut self,
+        ix: usize,
+        secondary: bool,
+        window: &mut Window,
+        cx: &mut Context<Picker<Self>>,
+    );
+}
+
+pub struct PickerItem {
+    pub label: SharedString,
+    /// Shown dimmed after the label, eg. the directory of a file.
+    pub detail: Option<SharedString>,
+    /// The byte offsets of the characters in the label to highlight.
+    pub highlights: Vec<usize>,
+}
+
+/// A modal list of matches for what the user types, eg. the file finder.
+pub struct Picker<D: PickerDelegate> {
+    pub delegate: D,
+    input: Entity<TextInput>,
+    selected: usize,
+    scroll: UniformListScrollHandle,
+    open: bool,
+    /// Where focus should go back to when the picker is closed.
+    previous_focus: Option<FocusHandle>,
+}
+
+impl<D: PickerDelegate> Picker<D> {
+    pub fn new(cx: &mut Context<Self>, delegate: D) -> Self {
+        let input = cx.new(|cx| TextInput::new(cx, "", delegate.placeholder()));
+        cx.subscribe(&input, |this, _, OnChange(query): &OnChange, cx| {
+            this.update_matches(query.to_string(), cx)
+        })
+        .detach();
+
+        Self {
+            delegate,
+            input,
+            selected: 0,
+            scroll: UniformListScrollHandle::new(),
+            open: false,
+            previous_focus: None,
+        }
+    }
+
+    pub fn toggle(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.open {
+            self.close(window, cx);
+            return;
+        }
+
+        self.open = true;
+        self.previous_focus = window.focused(cx);
+        self.input.update(cx, |input, cx| input.set_content("", cx));
+        self.input.read(cx).focus_handle.focus(window);
+        self.update_matches(String::new(), cx);
+    }
+
+    pub fn close(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.open = false;
+        self.delegate.dismissed();
+        if let Some(focus) = self.previous_focus.take() {
+            focus.focus(window);
+        }
+        cx.notify();
+    }
+
+    fn update_matches(&mut self, query: String, cx: &mut Context<Self>) {
+        self.delegate.update_matches(query, cx);
+        self.matches_updated(cx);
+    }
+
+    /// Select the best match again after the delegate has replaced its matches.
+    pub fn matches_updated(&mut self, cx: &mut Context<Self>) {
+        self.selected = 0;
+        self.scroll.scroll_to_item(0, ScrollStrategy::Top);
+        cx.notify();
+    }
+
+    fn select(&mut self, ix: usize, cx: &mut Context<Self>) {
+        self.selected = ix;
+        self.scroll.scroll_to_item(ix, ScrollStrategy::Top);
+        cx.notify();
+    }
+
+    fn select_previous(&mut self, _: &SelectPrevious, _: &mut Window, cx: &mut Context<Self>) {
+        let count = self.delegate.match_count();
+        if count > 0 {
+            self.select((self.selected + count - 1) % count, cx);
+        }
+    }
+
+    fn select_next(&mut self, _: &SelectNext, _: &mut Window, cx: &mut Context<Self>) {
+        let count = self.delegate.match_count();
+        if count > 0 {
+            self.select((self.selected + 1) % count, cx);
+        }
+    }
+
+    fn confirm_selected(&mut self, secondary: bool, window: &mut Window, cx: &mut Context<Self>) {
+        if self.selected >= self.delegate.match_count() {
+            return;
+        }
+        // Close first so whatever the delegate focuses keeps focus
+        self.close(window, cx);
+        self.delegate.confirm(self.selected, secondary, window, cx);
+    }
+
+    fn render_item(&self, ix: usize, window: &Window, cx: &mut Context<Self>) -> Stateful<Div> {
+        let item = self.delegate.render_match(ix);
+        let highlights = item
+            .highlights
+            .iter()
+            .filter_map(|&start| {
+                let len = item.label.get(start..)?.chars().next()?.len_utf8();
+                Some((
+                    start..start + len,
+                    HighlightStyle {
+                        font_weight: Some(FontWeight::BOLD),
+                        background_color: Some(rgba(0xffe06680).into()),
+                        ..Default::default()
+                    },
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        div()
+            .id(("picker-item", ix))
+            .flex()
+            .flex_row()
+            .gap_2()
+            .px_2()
+            .py_1()
+            .cursor_pointer()
+            .when(ix == self.selected, |this| this.bg(rgb(0xDDE6FF)))
+            .hover(|this| this.bg(rgb(0xF0F0F0)))
+            .child(
+                div().flex_none().child(
+                    StyledText::new(item.label.clone())
+                        .with_default_highlights(&window.text_style(), highlights),
+                ),
+            )
+            .when_some(item.detail, |this, detail| {
+                this.child(div().truncate().text_color(rgb(0x999999)).child(detail))
+            })
+            .on_click(cx.listener(move |this, event: &ClickEvent, window, cx| {
+                this.selected = ix;
+                let secondary = event.down.modifiers.secondary();
+                this.confirm_selected(secondary, window, cx);
+            }))
+    }
+}
+
+impl<D: PickerDelegate> Render for Picker<D> {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !self.open {
+            return div().into_any();
+        }
+
+        let count = self.delegate.match_count();
+        let notice = self.delegate.notice().filter(|_| count == 0);
+        // The notice takes up a row
+        let rows = count.max(notice.is_some() as usize);
+        div()
+            .id("picker")
+            .key_context("Picker")
+            .on_action(cx.listener(Self::select_previous))
+            .on_action(cx.listener(Self::select_next))
+            .on_action(
+                cx.listener(|this, _: &Confirm, window, cx| {
+                    this.confirm_selected(false, window, cx)
+                }),
+            )
+            .on_action(cx.listener(|this, _: &SecondaryConfirm, window, cx| {
+                this.confirm_selected(true, window, cx)
+            }))
+            .on_action(cx.listener(|this, _: &Dismiss, window, cx| this.close(window, cx)))
+            .absolute()
+            .inset_0()
+            .size_full()
+            .flex()
+            .justify_center()
+            .pt(px(80.))
+            .bg(black().opacity(0.3))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, _, window, cx| this.close(window, cx)),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .w(relative(0.6))
+                    .max_h(px(420.))
+                    .h(px(40. + 26. * rows.min(14) as f32))
+                    .p_2()
+                    .gap_2()
+                    .bg(white())
+                    .text_sm()
+                    .rounded_md()
+                    // Clicking inside the picker shouldn't close it
+                    .on_mouse_down(MouseButton::Left, |_, _, cx| cx.stop_propagation())
+                    .child(
+                        div()
+                            .border_1()
+                            .border_color(rgb(0x3311ff))
+                            .child(self.input.clone()),
+                    )
+                    .when_some(notice, |this, notice| {
+                        this.child(div().px_1().text_color(rgb(0x555555)).child(notice))
+                    })
+                    .child(
+                        uniform_list(cx.entity(), "picker-items", count, {
+                            move |this, range, window, cx| {
+                                range
+                                    .map(|ix| this.render_item(ix, window, cx))
+                                    .collect::<Vec<_>>()
+                            }
+                        })
+                        .flex_1()
+                        .track_scroll(self.scroll.clone()),
+                    ),
+            )
+            .into_any()
+    }
+}
@@ -0,0 +1,743 @@
+use chrono::{DateTime, Local};
+use gpui::{prelude::FluentBuilder, *};
+use human_bytes::human_bytes;
+
+use crate::{
+    metadata::{Properties, Totals, Users, mode_string},
+    permissions::{self, MODE_MASK, ModeChange, PermissionChange},
+    state::State,
+    xattrs::{self, Xattr},
+};
+
+use super::{Menu, MenuItem, TextInput, button, button2};
+
+enum Info {
+    Single(Properties),
+    Multiple(Totals),
+}
+
+/// The pending changes to the permissions and ownership of the selection.
+struct PermissionEditor {
+    /// The mode for the selection, and directories when recursing. With multiple nodes selected
+    /// this starts as the first one's mode.
+    mode: u32,
+    /// The mode for files when recursing.
+    file_mode: u32,
+    /// The bits of `mode` and `file_mode` the user has changed, which are the only ones applied.
+    edited_mode: u32,
+    edited_file_mode: u32,
+    original_uid: u32,
+    original_gid: u32,
+    uid: u32,
+    gid: u32,
+    recursive: bool,
+    applying: bool,
+    errors: Vec<String>,
+}
+
+impl PermissionEditor {
+    fn new(properties: &Properties) -> Self {
+        let mode = properties.mode & MODE_MASK;
+        Self {
+            mode,
+            // Files inside of a directory shouldn't become executable just because the directory is searchable
+            file_mode: mode & !0o111 & !0o7000,
+            edited_mode: 0,
+            edited_file_mode: 0,
+            original_uid: properties.uid,
+            original_gid: properties.gid,
+            uid: properties.uid,
+            gid: properties.gid,
+            recursive: false,
+            applying: false,
+            errors: Vec::new(),
+        }
+    }
+
+    fn change(&self) -> PermissionChange {
+        PermissionChange {
+            mode: ModeChange {
+                mask: self.edited_mode,
+                bits: self.mode,
+            },
+            file_mode: ModeChange {
+                mask: self.edited_file_mode,
+                bits: self.file_mode,
+            },
+            uid: (self.uid != self.original_uid).then_some(self.uid),
+            gid: (self.gid != self.original_gid).then_some(self.gid),
+            recursive: self.recursive,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum OwnerMenu {
+    User,
+    Group,
+}
+
+/// Which of the editor's modes a grid edits.
+#[derive(Clone, Copy)]
+enum ModeField {
+    Mode,
+    FileMode,
+}
+
+/// Shows the Unix metadata of the selection next to the table.
+pub struct PropertiesPanel {
+    state: Entity<State>,
+    users: Users,
+    info: Option<Result<Info, String>>,
+    editor: Option<PermissionEditor>,
+    owner_menu: Option<(Point<Pixels>, OwnerMenu)>,
+    /// The extended attributes of a single selected node.
+    xattrs: Option<Result<Vec<Xattr>, String>>,
+    /// Show every value as hex instead of only the binary ones.
+    xattr_hex: bool,
+    xattr_name: Entity<TextInput>,
+    xattr_value: Entity<TextInput>,
+    xattr_error: Option<String>,
+    open: bool,
+}
+
+impl PropertiesPanel {
+    pub fn init(cx: &mut Context<Self>, state: Entity<State>) -> Self {
+        cx.observe(&state, |this, _, cx| {
+            if this.open {
+                this.refresh(cx);
+            }
+        })
+        .detach();
+
+        Self {
+            state,
+            users: Users::default(),
+            info: None,
+            editor: None,
+            owner_menu: None,
+            xattrs: None,
+            xattr_hex: false,
+            xattr_name: cx.new(|cx| TextInput::new(cx, xattrs::EDITABLE_PREFIX, "Name")),
+            xattr_value: cx.new(|cx| TextInput::new(cx, "", "Value")),
+            xattr_error: None,
+            open: false,
+        }
+    }
+
+    pub fn open(&mut self, cx: &mut Context<Self>) {
+        self.open = true;
+        // Users and groups can change while we are running so load them again each time
+        self.users = Users::load();
+        self.refresh(cx);
+    }
+
+    pub fn close(&mut self, cx: &mut Context<Self>) {
+        self.open = false;
+        self.info = None;
+        self.editor = None;
+        self.owner_menu = None;
+        self.xattrs = None;
+        cx.notify();
+    }
+
+    pub fn toggle(&mut self, cx: &mut Context<Self>) {
+        if self.open {
+            self.close(cx);
+        } else {
+            self.open(cx);
+        }
+    }
+
+    fn refresh(&mut self, cx: &mut Context<Self>) {
+        let nodes = self.state.read(cx).selected_nodes();
+        self.info = match nodes.as_slice() {
+            [] => None,
+            [node] => Some(
+                Properties::load(&node.path)
+                    .map(Info::Single)
+                    .map_err(|err| err.to_string()),
+            ),
+            nodes => Some(Ok(Info::Multiple(Totals::load(
+                nodes.iter().map(|node| node.path.as_path()),
+            )))),
+        };
+        // Edits are for the old selection so start again
+        self.editor = nodes
+            .first()
+            .and_then(|node| Properties::load(&node.path).ok())
+            .map(|properties| PermissionEditor::new(&properties));
+        self.owner_menu = None;
+        self.xattrs = match nodes.as_slice() {
+            [node] => Some(xattrs::list(&node.path).map_err(|err| err.to_string())),
+            _ => None,
+        };
+        self.xattr_error = None;
+        cx.notify();
+    }
+
+    /// Load an attribute into the inputs so it can be changed.
+    fn edit_xattr(&mut self, xattr: &Xattr, cx: &mut Context<Self>) {
+        // TODO: Support editing binary values
+        let value = xattr.text().unwrap_or_default().to_string();
+        self.xattr_name
+            .update(cx, |input, cx| input.set_content(xattr.name.clone(), cx));
+        self.xattr_value
+            .update(cx, |input, cx| input.set_content(value, cx));
+    }
+
+    fn set_xattr(&mut self, cx: &mut Context<Self>) {
+        let Some(node) = self.state.read(cx).selected_nodes().into_iter().next() else {
+            return;
+        };
+        let name = self.xattr_name.read(cx).content.to_string();
+        let value = self.xattr_value.read(cx).content.to_string();
+
+        match xattrs::set(&node.path, &name, value.as_bytes()) {
+            Ok(()) => self.refresh(cx),
+            Err(err) => {
+                self.xattr_error = Some(format!("Failed to set {name}: {err}"));
+                cx.notify();
+            }
+        }
+    }
+
+    fn remove_xattr(&mut self, name: &str, cx: &mut Context<Self>) {
+        let Some(node) = self.state.read(cx).selected_nodes().into_iter().next() else {
+            return;
+        };
+
+        match xattrs::remove(&node.path, name) {
+            Ok(()) => self.refresh(cx),
+            Err(err) => {
+                self.xattr_error = Some(format!("Failed to remove {name}: {err}"));
+                cx.notify();
+            }
+        }
+    }
+
+    fn apply_permissions(&mut self, cx: &mut Context<Self>) {
+        let Some(editor) = &mut self.editor else {
+            return;
+        };
+        if editor.applying {
+            return;
+        }
+
+        let paths = self
+            .state
+            .read(cx)
+            .selected_nodes()
+            .iter()
+            .map(|node| node.path.clone())
+            .collect::<Vec<_>>();
+        let change = editor.change();
+        editor.applying = true;
+        editor.errors.clear();
+        cx.notify();
+
+        cx.spawn(async move |this, cx| {
+            let errors = cx
+                .background_executor()
+                .spawn(async move { permissions::apply(&paths, &change) })
+                .await;
+
+            this.update(cx, |this, cx| {
+                this.refresh(cx);
+                if let Some(editor) = &mut this.editor {
+                    editor.errors = errors;
+                }
+            })
+            .ok();
+        })
+        .detach();
+    }
+}
+
+fn row(label: &'static str, value: impl Into<SharedString>) -> Div {
+    labelled(label, div().flex_1().overflow_hidden().child(value.into()))
+}
+
+fn labelled(label: &'static str, child: impl IntoElement) -> Div {
+    div()
+        .flex()
+        .flex_row()
+        .gap_2()
+        .child(
+            div()
+                .w(px(90.))
+                .flex_none()
+                .text_color(rgb(0x555555))
+                .child(label),
+        )
+        .child(child)
+}
+
+fn time(time: Option<DateTime<Local>>) -> String {
+    time.map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "--".into())
+}
+
+fn size(bytes: u64) -> String {
+    format!("{} ({bytes} bytes)", human_bytes(bytes as f64)) // TODO: This cast is bad
+}
+
+impl PropertiesPanel {
+    fn render_single(&self, properties: &Properties) -> Div {
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .child(row("Path", properties.path.to_string_lossy().to_string()))
+            .child(row("Kind", properties.kind))
+            .when_some(properties.link_target.as_ref(), |this, target| {
+                this.child(row("Target", target.to_string_lossy().to_string()))
+            })
+            .child(row("Size", size(properties.size)))
+            .child(row("On disk", size(properties.allocated())))
+            .child(row(
+                "Mode",
+                format!(
+                    "{} ({:04o})",
+                    mode_string(properties.mode),
+                    properties.mode & 0o7777
+                ),
+            ))
+            .child(row(
+                "Owner",
+                format!(
+                    "{} ({})",
+                    self.users.user_name(properties.uid),
+                    properties.uid
+                ),
+            ))
+            .child(row(
+                "Group",
+                format!(
+                    "{} ({})",
+                    self.users.group_name(properties.gid),
+                    properties.gid
+                ),
+            ))
+            .child(row("Inode", properties.inode.to_string()))
+            .child(row("Device", properties.device()))
+            .child(row("Links", properties.links.to_string()))
+            .child(row("Block size", properties.block_size.to_string()))
+            .child(row("Blocks", properties.blocks.to_string()))
+            .child(row("Accessed", time(properties.accessed)))
+            .child(row("Modified", time(properties.modified)))
+            .child(row("Changed", time(properties.changed)))
+            .child(row("Created", time(properties.created)))
+    }
+
+    fn render_multiple(&self, totals: &Totals) -> Div {
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .child(row("Items", totals.count().to_string()))
+            .child(row("Files", totals.files.to_string()))
+            .child(row("Folders", totals.directories.to_string()))
+            .when(totals.other > 0, |this| {
+                this.child(row("Other", totals.other.to_string()))
+            })
+            .child(row("Size", size(totals.size)))
+            .child(row("On disk", size(totals.allocated)))
+            .when(totals.errors > 0, |this| {
+                this.child(
+                    div()
+                        .text_color(red())
+                        .child(format!("Failed to read {} items", totals.errors)),
+                )
+            })
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(rgb(0x999999))
+                    .child("Folder sizes don't include their contents"),
+            )
+    }
+}
+
+fn checkbox(id: impl Into<ElementId>, checked: bool) -> Stateful<Div> {
+    div()
+        .id(id)
+        .flex()
+        .items_center()
+        .justify_center()
+        .w(px(24.))
+        .cursor_pointer()
+        .child(if checked { "[x]" } else { "[ ]" })
+}
+
+impl PropertiesPanel {
+    /// A owner/group/other × read/write/execute grid, plus the special bits.
+    fn render_mode_grid(
+        &self,
+        title: &'static str,
+        field: ModeField,
+        mode: u32,
+        cx: &Context<Self>,
+    ) -> Div {
+        let toggle = |bit: u32| {
+            cx.listener(move |this: &mut Self, _: &ClickEvent, _, cx| {
+                if let Some(editor) = &mut this.editor {
+                    match field {
+                        ModeField::Mode => {
+                            editor.mode ^= bit;
+                            editor.edited_mode ^= bit;
+                        }
+                        ModeField::FileMode => {
+                            editor.file_mode ^= bit;
+                            editor.edited_file_mode ^= bit;
+                        }
+                    }
+                    cx.notify();
+                }
+            })
+        };
+        let header = |label: &'static str| div().w(px(24.)).text_xs().child(label);
+
+        div()
+            .flex()
+            .flex_col()
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .justify_between()
+                    .child(div().text_color(rgb(0x555555)).child(title))
+                    .child(format!("{} ({:04o})", mode_string(mode), mode & MODE_MASK)),
+            )
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .child(div().w(px(60.)))
+                    .child(header("r"))
+                    .child(header("w"))
+                    .child(header("x")),
+            )
+            .children(
+                [("Owner", 6), ("Group", 3), ("Other", 0)].map(|(label, shift)| {
+                    div()
+                        .flex()
+                        .flex_row()
+                        .child(div().w(px(60.)).child(label))
+                        .children([0o4, 0o2, 0o1].map(|bit| {
+                            let bit = bit << shift;
+                            checkbox(
+                                SharedString::from(format!("{title}-{bit}")),
+                                mode & bit != 0,
+                            )
+                            .on_click(toggle(bit))
+                        }))
+                }),
+            )
+            .child(div().flex().flex_row().gap_2().children(
+                [("setuid", 0o4000), ("setgid", 0o2000), ("sticky", 0o1000)].map(|(label, bit)| {
+                    div()
+                        .flex()
+                        .flex_row()
+                        .child(
+                            checkbox(
+                                SharedString::from(format!("{title}-{bit}")),
+                                mode & bit != 0,
+                            )
+                            .on_click(toggle(bit)),
+                        )
+                        .child(label)
+                }),
+            ))
+    }
+
+    fn render_editor(&self, editor: &PermissionEditor, cx: &Context<Self>) -> Div {
+        let is_dir = matches!(
+            &self.info,
+            Some(Ok(Info::Single(properties))) if properties.kind == "Directory"
+        ) || matches!(&self.info, Some(Ok(Info::Multiple(totals))) if totals.directories > 0);
+
+        let owner_button = |id: &'static str, label: String, menu: OwnerMenu| {
+            div()
+                .id(id)
+                .flex_1()
+                .px_2()
+                .border_1()
+                .border_color(rgb(0xE0E0E0))
+                .rounded_sm()
+                .cursor_pointer()
+                .child(format!("{label} ▾"))
+                .on_click(cx.listener(move |this, _, window, cx| {
+                    this.owner_menu = Some((window.mouse_position(), menu));
+                    cx.notify();
+                }))
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_2()
+            .pt_2()
+            .border_t_1()
+            .border_color(rgb(0xE0E0E0))
+            .child(self.render_mode_grid(
+                if editor.recursive {
+                    "Folders"
+                } else {
+                    "Permissions"
+                },
+                ModeField::Mode,
+                editor.mode,
+                cx,
+            ))
+            .when(editor.recursive, |this| {
+                this.child(self.render_mode_grid(
+                    "Files",
+                    ModeField::FileMode,
+                    editor.file_mode,
+                    cx,
+                ))
+            })
+            .child(labelled(
+                "Owner",
+                owner_button("owner", self.users.user_name(editor.uid), OwnerMenu::User),
+            ))
+            .child(labelled(
+                "Group",
+                owner_button("group", self.users.group_name(editor.gid), OwnerMenu::Group),
+            ))
+            .when(is_dir, |this| {
+                this.child(
+                    div()
+                        .id("recursive")
+                        .flex()
+                        .flex_row()
+                        .gap_1()
+                        .cursor_pointer()
+                        .child(if editor.recursive { "[x]" } else { "[ ]" })
+                        .child("Apply to enclosed files and folders")
+                        .on_click(cx.listener(|this, _, _, cx| {
+                            if let Some(editor) = &mut this.editor {
+                                editor.recursive = !editor.recursive;
+                                cx.notify();
+                            }
+                        })),
+                )
+            })
+            .child(div().flex().flex_row().justify_end().child(button2(
+                if editor.applying {
+                    "Applying..."
+                } else {
+                    "Apply"
+                },
+                editor.applying,
+                {
+                    let entity = cx.entity();
+                    move |_, cx| entity.update(cx, |this, cx| this.apply_permissions(cx))
+                },
+            )))
+            .children(
+                editor
+                    .errors
+                    .iter()
+                    .map(|err| div().text_xs().text_color(red()).child(err.clone())),
+            )
+    }
+
+    fn render_xattrs(&self, xattrs: &[Xattr], cx: &Context<Self>) -> Div {
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .pt_2()
+            .border_t_1()
+            .border_color(rgb(0xE0E0E0))
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .justify_between()
+                    .child("Extended attributes")
+                    .child(button(if self.xattr_hex { "Text" } else { "Hex" }, {
+                        let entity = cx.entity();
+                        move |_, cx| {
+                            entity.update(cx, |this, cx| {
+                                this.xattr_hex = !this.xattr_hex;
+                                cx.notify();
+                            })
+                        }
+                    })),
+            )
+            .when(xattrs.is_empty(), |this| {
+                this.child(div().text_color(rgb(0x999999)).child("None"))
+            })
+            .children(xattrs.iter().map(|xattr| {
+                let value = match (xattr.acl(&self.users), xattr.text()) {
+                    (Some(acl), _) if !self.xattr_hex => acl.join("\n"),
+                    (_, Some(text)) if !self.xattr_hex => text.to_string(),
+                    _ => xattr.hex(),
+                };
+
+                div()
+                    .id(SharedString::from(xattr.name.clone()))
+                    .flex()
+                    .flex_col()
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .justify_between()
+                            .child(div().text_color(rgb(0x555555)).child(xattr.name.clone()))
+                            .when(xattr.is_editable(), |this| {
+                                let entity = cx.entity();
+                                this.child(
+                                    div()
+                                        .flex()
+                                        .flex_row()
+                                        .gap_1()
+                                        .child(button("Edit", {
+                                            let entity = entity.clone();
+                                            let xattr = xattr.clone();
+                                            move |_, cx| {
+                                                entity.update(cx, |this, cx| {
+                                                    this.edit_xattr(&xattr, cx)
+                                                })
+                                            }
+                                        }))
+                                        .child(button("Remove", {
+                                            let name = xattr.name.clone();
+                                            move |_, cx| {
+                                                entity.update(cx, |this, cx| {
+                                                    this.remove_xattr(&name, cx)
+                                                })
+                                            }
+                                        })),
+                                )
+                            }),
+                    )
+                    .child(div().text_xs().child(value))
+            }))
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .gap_1()
+                    .child(
+                        div()
+                            .flex_1()
+                            .border_1()
+                            .border_color(rgb(0xE0E0E0))
+                            .child(self.xattr_name.clone()),
+                    )
+                    .child(
+                        div()
+                            .flex_1()
+                            .border_1()
+                            .border_color(rgb(0xE0E0E0))
+                            .child(self.xattr_value.clone()),
+                    )
+                    .child(button("Set", {
+                        let entity = cx.entity();
+                        move |_, cx| entity.update(cx, |this, cx| this.set_xattr(cx))
+                    })),
+            )
+            .when_some(self.xattr_error.clone(), |this, err| {
+                this.child(div().text_xs().text_color(red()).child(err))
+            })
+    }
+
+    fn render_owner_menu(
+        &self,
+        position: Point<Pixels>,
+        menu: OwnerMenu,
+        cx: &mut Context<Self>,
+    ) -> Menu {
+        let entity = cx.entity();
+        let ids = match menu {
+            OwnerMenu::User => &self.users.users,
+            OwnerMenu::Group => &self.users.groups,
+        };
+
+        Menu::new(
+            position,
+            ids.iter()
+                .map(|(id, name)| {
+                    let (id, entity) = (*id, entity.clone());
+                    MenuItem::entry(format!("{name} ({id})"), move |_, cx| {
+                        entity.update(cx, |this, cx| {
+                            if let Some(editor) = &mut this.editor {
+                                match menu {
+                                    OwnerMenu::User => editor.uid = id,
+                                    OwnerMenu::Group => editor.gid = id,
+                                }
+                                cx.notify();
+                            }
+                        })
+                    })
+                })
+                .collect(),
+            move |_, cx| {
+                entity.update(cx, |this, cx| {
+                    this.owner_menu = None;
+                    cx.notify();
+                })
+            },
+        )
+    }
+}
+
+impl Render for PropertiesPanel {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if !self.open {
+            return div().into_any();
+        }
+
+        div()
+            .id("properties-panel")
+            .flex()
+            .flex_col()
+            .flex_none()
+            .gap_2()
+            .w(px(320.))
+            .h_full()
+            .p_2()
+            .overflow_y_scroll()
+            .bg(white())
+            .text_sm()
+            .border_l_1()
+            .border_color(rgb(0xE0E0E0))
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .justify_between()
+                    .child("Properties")
+                    .child(button("Close", {
+                        let entity = cx.entity();
+                        move |_, cx| entity.update(cx, |this, cx| this.close(cx))
+                    })),
+            )
+            .child(match &self.info {
+                None => div().text_color(rgb(0x999999)).child("Nothing selected"),
+                Some(Err(err)) => div().text_color(red()).child(err.clone()),
+                Some(Ok(Info::Single(properties))) => self.render_single(properties),
+                Some(Ok(Info::Multiple(totals))) => self.render_multiple(totals),
+            })
+            .when_some(self.editor.as_ref(), |this, editor| {
+                this.child(self.render_editor(editor, cx))
+            })
+            .map(|this| match &self.xattrs {
+                Some(Ok(xattrs)) => this.child(self.render_xattrs(xattrs, cx)),
+                Some(Err(err)) => this.child(
+                    div()
+                        .text_color(red())
+                        .child(format!("Failed to read extended attributes: {err}")),
+                ),
+                None => this,
+            })
+            .when_some(self.owner_menu, |this, (position, menu)| {
+                this.child(self.render_owner_menu(position, menu, cx))
+            })
+            .into_any()
+    }
+}
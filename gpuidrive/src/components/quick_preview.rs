@@ -1,29 +1,193 @@
-use std::{rc::Rc, time::Duration};
+use std::{ops::Range, path::PathBuf, rc::Rc, time::Duration};
 
-use gpui::*;
+use chrono::{DateTime, Local};
+use gpui::{prelude::FluentBuilder, *};
+use human_bytes::human_bytes;
 use opener::open;
 
-use crate::state::{Node, NodeKind, State};
+use crate::{
+    archive::{self, Format},
+    hex::{self, BYTES_PER_ROW, Magic},
+    preview::{self, Line, Preview, TextPreview},
+    state::{Node, NodeKind, State},
+};
+
+use super::TextInput;
+
+const MONOSPACE: &str = if cfg!(target_os = "macos") {
+    "Menlo"
+} else {
+    "DejaVu Sans Mono"
+};
+/// How many rows either side of the visible ones the hex view reads, so scrolling doesn't read
+/// the file every frame.
+const HEX_MARGIN_ROWS: usize = 256;
 
 pub struct QuickPreview {
     node: Option<Rc<Node>>,
+    /// The line to scroll to and highlight in text previews.
+    line: Option<usize>,
+    scroll: UniformListScrollHandle,
+    /// The file and modification time `preview` was loaded for, so it's only reloaded when it changes.
+    preview_key: Option<(PathBuf, DateTime<Local>)>,
+    /// `None` while it's loading.
+    preview: Option<Rc<Result<Preview, String>>>,
+    _load: Option<Task<()>>,
+    /// The bytes of a binary file around the visible rows: where they start, how many were
+    /// requested and what was read.
+    hex_window: Option<(u64, usize, Vec<u8>)>,
+    /// The byte jumped to with the "Go to offset" input.
+    hex_target: Option<u64>,
+    goto: Entity<TextInput>,
     toggle: bool,
+    focus_handle: FocusHandle,
+    /// Where focus should go back to when the preview is closed.
+    previous_focus: Option<FocusHandle>,
 }
 
 impl QuickPreview {
-    pub fn init() -> Self {
+    pub fn init(cx: &mut Context<Self>) -> Self {
         Self {
             node: None,
+            line: None,
+            scroll: UniformListScrollHandle::new(),
+            preview_key: None,
+            preview: None,
+            _load: None,
+            hex_window: None,
+            hex_target: None,
+            goto: cx.new(|cx| TextInput::new(cx, "", "Go to offset, eg. 0x1F0")),
             toggle: false,
+            focus_handle: cx.focus_handle(),
+            previous_focus: None,
         }
     }
 
     pub fn set(&mut self, node: Option<Rc<Node>>) {
         self.node = node;
+        self.line = None;
+    }
+
+    /// Open the preview of a text file scrolled to `line`.
+    pub fn show_line(
+        &mut self,
+        node: Rc<Node>,
+        line: usize,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.node = Some(node);
+        self.line = Some(line);
+        self.scroll_to_line();
+        if !self.toggle {
+            self.toggle(window, cx);
+        }
+        cx.notify();
     }
 
-    pub fn toggle(&mut self) {
+    pub fn toggle(&mut self, window: &mut Window, cx: &mut Context<Self>) {
         self.toggle = !self.toggle;
+        if self.toggle {
+            self.previous_focus = window.focused(cx);
+            self.focus_handle.focus(window);
+        } else if let Some(focus) = self.previous_focus.take() {
+            focus.focus(window);
+        }
+        cx.notify();
+    }
+
+    /// Scroll a text preview to `line`, if that part of the file has been read.
+    fn scroll_to_line(&mut self) {
+        if let (Some(line), Some(Ok(Preview::Text(text)))) = (self.line, self.preview.as_deref())
+            && text.contains(line)
+        {
+            self.scroll
+                .scroll_to_item(line - text.first_line, ScrollStrategy::Top);
+        }
+    }
+
+    /// Read and highlight `node` in the background, unless it already has been.
+    fn load_preview(&mut self, node: &Node, cx: &mut Context<Self>) {
+        let key = (node.path.clone(), node.modified);
+        // Only part of large files is read, so a line outside of it needs another part
+        let missing_line = match (self.line, self.preview.as_deref()) {
+            (Some(line), Some(Ok(Preview::Text(text)))) => !text.contains(line),
+            _ => false,
+        };
+        if self.preview_key.as_ref() == Some(&key) && !missing_line {
+            return;
+        }
+        self.preview_key = Some(key);
+        self.preview = None;
+        self.hex_window = None;
+        self.hex_target = None;
+
+        let (path, line) = (node.path.clone(), self.line);
+        let task = cx
+            .background_executor()
+            .spawn(async move { preview::load(&path, line).map_err(|err| err.to_string()) });
+        self._load = Some(cx.spawn(async move |this, cx| {
+            let preview = task.await;
+            this.update(cx, |this, cx| {
+                this.preview = Some(Rc::new(preview));
+                this.scroll_to_line();
+                cx.notify();
+            })
+            .ok();
+        }));
+    }
+
+    /// The bytes of `rows` of the binary file being previewed. If they can't be read the preview
+    /// shows the error instead.
+    fn hex_bytes(&mut self, rows: Range<usize>, cx: &mut Context<Self>) -> Vec<u8> {
+        let Some((path, _)) = &self.preview_key else {
+            return Vec::new();
+        };
+        let start = (rows.start * BYTES_PER_ROW) as u64;
+        let len = rows.len() * BYTES_PER_ROW;
+
+        let cached = self
+            .hex_window
+            .as_ref()
+            .is_some_and(|(offset, requested, _)| {
+                *offset <= start && start + len as u64 <= offset + *requested as u64
+            });
+        if !cached {
+            let offset = (rows.start.saturating_sub(HEX_MARGIN_ROWS) * BYTES_PER_ROW) as u64;
+            let requested = (start - offset) as usize + len + HEX_MARGIN_ROWS * BYTES_PER_ROW;
+            match hex::read_window(path, offset, requested) {
+                Ok(bytes) => self.hex_window = Some((offset, requested, bytes)),
+                Err(err) => {
+                    let err = format!("Failed to read {}: {err}", path.display());
+                    self.preview = Some(Rc::new(Err(err)));
+                    cx.notify();
+                    return Vec::new();
+                }
+            }
+        }
+
+        let Some((offset, _, bytes)) = &self.hex_window else {
+            return Vec::new();
+        };
+        let start = ((start - offset) as usize).min(bytes.len());
+        bytes[start..(start + len).min(bytes.len())].to_vec()
+    }
+
+    fn go_to_offset(&mut self, len: u64, cx: &mut Context<Self>) {
+        let text = self.goto.read(cx).content.clone();
+        match hex::parse_offset(&text).filter(|offset| *offset < len) {
+            Some(offset) => {
+                self.hex_target = Some(offset);
+                self.scroll
+                    .scroll_to_item(offset as usize / BYTES_PER_ROW, ScrollStrategy::Top);
+                self.goto
+                    .update(cx, |goto, cx| goto.set_error_range(None, cx));
+            }
+            None => self
+                .goto
+                .update(cx, |goto, cx| goto.set_error_range(Some(0..text.len()), cx)),
+        }
+        cx.notify();
     }
 }
 
@@ -38,10 +202,25 @@ impl Render for QuickPreview {
         }
         let Some(node) = self.node.clone() else {
             self.toggle = false;
+            if let Some(focus) = self.previous_focus.take() {
+                focus.focus(window);
+            }
             return div().into_any();
         };
 
+        // TODO: Use gpui::Img::extensions() for image detection
+        let is_image = node.path.extension().is_some_and(|ext| ext == "png");
+        if node.kind == NodeKind::File && !is_image {
+            self.load_preview(&node, cx);
+        }
+        let preview = self.preview.clone();
+        let scroll = self.scroll.clone();
+        let goto = self.goto.clone();
+        let entity = cx.entity();
+
         div()
+            .key_context("QuickPreview")
+            .track_focus(&self.focus_handle)
             .with_animation(
                 "loading-bg",
                 Animation::new(Duration::from_millis(100)),
@@ -66,6 +245,8 @@ impl Render for QuickPreview {
                                     div()
                                         .w(relative(0.5))
                                         .h(relative(0.5))
+                                        .flex()
+                                        .flex_col()
                                         .bg(gpui::black().alpha(delta * 0.2))
                                         .child(
                                             div()
@@ -75,24 +256,47 @@ impl Render for QuickPreview {
                                                 .bg(rgb(0x696969))
                                                 .child(node.name.to_string_lossy().to_string()),
                                         )
-                                        .child(div().opacity(delta).child(match node.kind {
-                                            NodeKind::File => match node.path.extension() {
-                                                // TODO: Wayyy smarter matching
+                                        .child(div().flex_1().opacity(delta).child(
+                                            match node.kind {
                                                 // TODO: Support more file types (video, audio, 3D model)
-                                                // TODO: Use gpui::Img::extensions() for image detection
-                                                Some(s) if s == "png" => image_preview(&node),
-                                                Some(s) if s == "txt" => text_preview(&node),
-                                                _ => {
+                                                NodeKind::File if is_image => image_preview(&node),
+                                                NodeKind::File => match preview.as_deref() {
+                                                    None => placeholder_preview("Loading…".into()),
+                                                    Some(Err(err)) => {
+                                                        placeholder_preview(err.clone())
+                                                    }
+                                                    Some(Ok(Preview::Archive {
+                                                        format,
+                                                        len,
+                                                        entries,
+                                                    })) => archive_preview(
+                                                        entity.clone(),
+                                                        *format,
+                                                        *len,
+                                                        entries,
+                                                        &scroll,
+                                                    ),
+                                                    Some(Ok(Preview::Binary { len, magic })) => {
+                                                        hex_preview(
+                                                            entity.clone(),
+                                                            *len,
+                                                            magic.as_ref(),
+                                                            goto.clone(),
+                                                            &scroll,
+                                                        )
+                                                    }
+                                                    Some(Ok(Preview::Text(text))) => {
+                                                        text_preview(entity.clone(), text, &scroll)
+                                                    }
+                                                },
+                                                NodeKind::Directory => placeholder_preview(
+                                                    node.name.to_str().unwrap().to_string(),
+                                                ),
+                                                NodeKind::Unknown => {
                                                     placeholder_preview("Unknown File Type!".into())
                                                 }
                                             },
-                                            NodeKind::Directory => placeholder_preview(
-                                                node.name.to_str().unwrap().to_string(),
-                                            ),
-                                            NodeKind::Unknown => {
-                                                placeholder_preview("Unknown File Type!".into())
-                                            }
-                                        })),
+                                        )),
                                 ),
                         )
                 },
@@ -101,21 +305,326 @@ impl Render for QuickPreview {
     }
 }
 
-fn text_preview(node: &Node) -> Div {
-    // TODO: Handle non-UTF8 file content
-    // TODO: We should probs be caching this between renders
-    let content = std::fs::read_to_string(&node.path).unwrap();
+fn text_preview(
+    entity: Entity<QuickPreview>,
+    text: &TextPreview,
+    scroll: &UniformListScrollHandle,
+) -> Div {
+    let mut details = format!("{} · {}", text.language, text.encoding.label());
+    if text.truncated && text.first_line > 0 {
+        details.push_str(" · Only part of the file is shown");
+    } else if text.truncated {
+        details.push_str(" · Only the start of the file is shown");
+    }
+    // Wide enough for the largest line number
+    let gutter = px(8. * (text.first_line + text.lines.len()).to_string().len() as f32 + 8.);
 
-    // ImageAssetLoader
+    div()
+        .flex()
+        .flex_col()
+        .size_full()
+        .bg(rgba(text.background))
+        .text_sm()
+        .child(
+            div()
+                .px_2()
+                .text_xs()
+                .text_color(rgb(0x555555))
+                .child(details),
+        )
+        .child(
+            uniform_list(
+                entity,
+                "text-preview",
+                text.lines.len(),
+                move |this, range, window, cx| {
+                    let Some(Ok(Preview::Text(text))) = this.preview.as_deref() else {
+                        return Vec::new();
+                    };
+                    range
+                        .map(|ix| {
+                            let line = text.first_line + ix;
+                            render_line(
+                                line,
+                                &text.lines[ix],
+                                this.line == Some(line),
+                                gutter,
+                                window,
+                            )
+                        })
+                        .collect()
+                },
+            )
+            .flex_1()
+            .font_family(MONOSPACE)
+            .track_scroll(scroll.clone()),
+        )
+}
+
+/// `number` is the zero-based line number in the file.
+fn render_line(
+    number: usize,
+    line: &Line,
+    highlighted: bool,
+    gutter: Pixels,
+    window: &Window,
+) -> Div {
+    let highlights = line
+        .spans
+        .iter()
+        .map(|span| {
+            (
+                span.range.clone(),
+                HighlightStyle {
+                    color: Some(rgba(span.color).into()),
+                    font_weight: span.bold.then_some(FontWeight::BOLD),
+                    font_style: span.italic.then_some(FontStyle::Italic),
+                    ..Default::default()
+                },
+            )
+        })
+        .collect::<Vec<_>>();
 
     div()
         .flex()
-        .justify_center()
-        .items_center()
+        .flex_row()
+        .gap_2()
+        .whitespace_nowrap()
+        .overflow_hidden()
+        .when(highlighted, |this| this.bg(rgba(0xffe06680)))
+        .child(
+            div()
+                .flex_none()
+                .w(gutter)
+                .flex()
+                .justify_end()
+                .text_color(rgb(0x999999))
+                .child((number + 1).to_string()),
+        )
+        .child(
+            StyledText::new(line.text.clone())
+                .with_default_highlights(&window.text_style(), highlights),
+        )
+}
+
+fn archive_preview(
+    entity: Entity<QuickPreview>,
+    format: Format,
+    len: u64,
+    entries: &[archive::Entry],
+    scroll: &UniformListScrollHandle,
+) -> Div {
+    let files = entries.iter().filter(|entry| !entry.is_dir).count();
+    let size = entries.iter().map(|entry| entry.size).sum::<u64>();
+    let mut details = format!(
+        "{} · {files} files · {} uncompressed",
+        format.label(),
+        human_bytes(size as f64)
+    );
+    if size > 0 {
+        details.push_str(&format!(" · compressed to {}", ratio(len, size)));
+    }
+
+    div()
+        .flex()
+        .flex_col()
         .size_full()
-        .bg(red())
-        .text_color(white())
-        .child(content)
+        .bg(white())
+        .text_sm()
+        .child(
+            div()
+                .px_2()
+                .text_xs()
+                .text_color(rgb(0x555555))
+                .child(details),
+        )
+        .child(
+            uniform_list(
+                entity,
+                "archive-preview",
+                entries.len(),
+                |this, range, _, _| {
+                    let Some(Ok(Preview::Archive { entries, .. })) = this.preview.as_deref() else {
+                        return Vec::new();
+                    };
+                    range.map(|ix| render_archive_entry(&entries[ix])).collect()
+                },
+            )
+            .flex_1()
+            .track_scroll(scroll.clone()),
+        )
+}
+
+/// How big `compressed` is compared to `size`, eg. "38%".
+fn ratio(compressed: u64, size: u64) -> String {
+    format!("{:.0}%", compressed as f64 / size as f64 * 100.)
+}
+
+fn render_archive_entry(entry: &archive::Entry) -> Div {
+    // TODO: Handle non-utf8 paths
+    let mut path = entry.path.to_string_lossy().to_string();
+    if entry.is_dir {
+        path.push('/');
+    }
+    let cell = |width: f32| div().flex_none().w(px(width)).flex().justify_end();
+
+    div()
+        .flex()
+        .flex_row()
+        .gap_2()
+        .px_2()
+        .whitespace_nowrap()
+        .child(div().flex_1().truncate().child(path))
+        .child(cell(80.).child(if entry.is_dir {
+            String::new()
+        } else {
+            human_bytes(entry.size as f64)
+        }))
+        .child(
+            cell(50.)
+                .text_color(rgb(0x999999))
+                .child(match entry.compressed_size {
+                    Some(compressed) if entry.size > 0 => ratio(compressed, entry.size),
+                    _ => String::new(),
+                }),
+        )
+        .child(
+            cell(130.).text_color(rgb(0x555555)).child(
+                entry
+                    .modified
+                    .map(|modified| modified.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default(),
+            ),
+        )
+}
+
+fn hex_preview(
+    entity: Entity<QuickPreview>,
+    len: u64,
+    magic: Option<&Magic>,
+    goto: Entity<TextInput>,
+    scroll: &UniformListScrollHandle,
+) -> Div {
+    let details = format!(
+        "{} · {}",
+        magic.map_or("Binary file", |magic| magic.name),
+        human_bytes(len as f64)
+    );
+    let rows = (len as usize).div_ceil(BYTES_PER_ROW);
+
+    div()
+        .flex()
+        .flex_col()
+        .size_full()
+        .bg(white())
+        .text_sm()
+        .child(
+            div()
+                .flex()
+                .flex_row()
+                .items_center()
+                .justify_between()
+                .gap_2()
+                .px_2()
+                .child(div().text_xs().text_color(rgb(0x555555)).child(details))
+                .child(
+                    div()
+                        .w(px(200.))
+                        .border_1()
+                        .border_color(rgb(0x3311ff))
+                        .on_key_down({
+                            let entity = entity.clone();
+                            move |event: &KeyDownEvent, _, cx| {
+                                if event.keystroke.key == "enter" {
+                                    entity.update(cx, |this, cx| this.go_to_offset(len, cx));
+                                }
+                            }
+                        })
+                        .child(goto),
+                ),
+        )
+        .child(
+            uniform_list(
+                entity,
+                "hex-preview",
+                rows,
+                move |this, range, window, _| {
+                    let Some(Ok(Preview::Binary { magic, .. })) = this.preview.as_deref() else {
+                        return Vec::new();
+                    };
+                    let magic = magic.clone();
+                    let target = this.hex_target;
+                    let bytes = this.hex_bytes(range.clone(), cx);
+                    range
+                        .zip(bytes.chunks(BYTES_PER_ROW))
+                        .map(|(row, bytes)| {
+                            render_hex_row(row, bytes, magic.as_ref(), target, window)
+                        })
+                        .collect()
+                },
+            )
+            .flex_1()
+            .font_family(MONOSPACE)
+            .track_scroll(scroll.clone()),
+        )
+}
+
+fn render_hex_row(
+    row: usize,
+    bytes: &[u8],
+    magic: Option<&Magic>,
+    target: Option<u64>,
+    window: &Window,
+) -> Div {
+    let start = (row * BYTES_PER_ROW) as u64;
+    // The bytes of this row in `range`
+    let local = |range: Range<u64>| {
+        let range = range.start.max(start)..range.end.min(start + bytes.len() as u64);
+        (range.start < range.end)
+            .then(|| (range.start - start) as usize..(range.end - start) as usize)
+    };
+    let mut highlights = Vec::new();
+    if let Some(range) = magic.and_then(|magic| local(magic.range.clone())) {
+        highlights.push((range, rgba(0x66b3ff66)));
+    }
+    if let Some(range) = target.and_then(|target| local(target..target + 1)) {
+        highlights.push((range, rgba(0xffe066cc)));
+    }
+
+    let style = |color: Rgba| HighlightStyle {
+        background_color: Some(color.into()),
+        ..Default::default()
+    };
+    let hex_highlights = highlights
+        .iter()
+        .map(|(range, color)| (hex::hex_range(range.clone()), style(*color)))
+        .collect::<Vec<_>>();
+    let ascii_highlights = highlights
+        .into_iter()
+        .map(|(range, color)| (range, style(color)))
+        .collect::<Vec<_>>();
+
+    div()
+        .flex()
+        .flex_row()
+        .gap_4()
+        .px_2()
+        .whitespace_nowrap()
+        .child(
+            div()
+                .text_color(rgb(0x999999))
+                .child(format!("{start:08x}")),
+        )
+        .child(
+            div().w(px(400.)).child(
+                StyledText::new(hex::hex(bytes))
+                    .with_default_highlights(&window.text_style(), hex_highlights),
+            ),
+        )
+        .child(
+            StyledText::new(hex::ascii(bytes))
+                .with_default_highlights(&window.text_style(), ascii_highlights),
+        )
 }
 
 fn image_preview(node: &Node) -> Div {
@@ -0,0 +1,270 @@
+use std::path::PathBuf;
+
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::{
+    config::{Config, SmartFolder},
+    query::Query,
+    state::State,
+};
+
+use super::{DataTable, TextInput, button, drop_target};
+
+/// The inputs for the smart folder being edited.
+struct Editor {
+    ix: usize,
+    name: Entity<TextInput>,
+    query: Entity<TextInput>,
+    root: Entity<TextInput>,
+    error: Option<String>,
+}
+
+/// Lists the user's smart folders, saved searches which are re-run when opened.
+pub struct Sidebar {
+    state: Entity<State>,
+    data_table: Entity<DataTable>,
+    smart_folders: Vec<SmartFolder>,
+    editing: Option<Editor>,
+}
+
+impl Sidebar {
+    pub fn init(state: Entity<State>, data_table: Entity<DataTable>) -> Self {
+        Self {
+            state,
+            data_table,
+            smart_folders: Config::load().smart_folders,
+            editing: None,
+        }
+    }
+
+    /// Save a new smart folder and start editing it so the user can name it.
+    pub fn add(&mut self, smart_folder: SmartFolder, cx: &mut Context<Self>) {
+        self.smart_folders.push(smart_folder);
+        self.save();
+        self.edit(self.smart_folders.len() - 1, cx);
+    }
+
+    fn save(&self) {
+        let smart_folders = self.smart_folders.clone();
+        Config::update(move |config| config.smart_folders = smart_folders);
+    }
+
+    /// Show the smart folder's search results as a virtual directory.
+    fn open(&mut self, ix: usize, cx: &mut Context<Self>) {
+        let Some(smart_folder) = self.smart_folders.get(ix).cloned() else {
+            return;
+        };
+
+        self.state
+            .update(cx, |state, cx| state.open_smart_folder(cx, smart_folder));
+    }
+
+    fn edit(&mut self, ix: usize, cx: &mut Context<Self>) {
+        let Some(smart_folder) = self.smart_folders.get(ix) else {
+            return;
+        };
+
+        self.editing = Some(Editor {
+            ix,
+            name: cx.new(|cx| TextInput::new(cx, smart_folder.name.clone(), "Name")),
+            query: cx.new(|cx| TextInput::new(cx, smart_folder.query.clone(), "Query")),
+            // TODO: Handle non-utf8 paths
+            root: cx.new(|cx| {
+                TextInput::new(
+                    cx,
+                    smart_folder.root.to_string_lossy().to_string(),
+                    "Folder",
+                )
+            }),
+            error: None,
+        });
+        cx.notify();
+    }
+
+    fn commit_edit(&mut self, cx: &mut Context<Self>) {
+        let Some(editor) = &mut self.editing else {
+            return;
+        };
+        let Some(mode) = self.smart_folders.get(editor.ix).map(|folder| folder.mode) else {
+            return;
+        };
+
+        let name = editor.name.read(cx).content.trim().to_string();
+        let query = editor.query.read(cx).content.to_string();
+        let root = PathBuf::from(editor.root.read(cx).content.to_string());
+
+        let error = if name.is_empty() {
+            Some("The name can't be empty".to_string())
+        } else if !root.is_dir() {
+            Some(format!("{} is not a folder", root.display()))
+        } else {
+            match Query::parse(&query, mode) {
+                Ok(query) if query.is_empty() => Some("The query can't be empty".to_string()),
+                Ok(_) => None,
+                Err(err) => {
+                    editor.query.update(cx, |input, cx| {
+                        input.set_error_range(Some(err.range.clone()), cx)
+                    });
+                    Some(err.message)
+                }
+            }
+        };
+        if error.is_some() {
+            editor.error = error;
+            cx.notify();
+            return;
+        }
+
+        let ix = editor.ix;
+        self.smart_folders[ix] = SmartFolder {
+            name,
+            root,
+            query,
+            mode,
+        };
+        self.editing = None;
+        self.save();
+        cx.notify();
+    }
+
+    fn remove(&mut self, ix: usize, cx: &mut Context<Self>) {
+        if ix < self.smart_folders.len() {
+            self.smart_folders.remove(ix);
+            self.editing = None;
+            self.save();
+            cx.notify();
+        }
+    }
+
+    fn render_editor(&self, editor: &Editor, cx: &Context<Self>) -> Div {
+        let input = |input: &Entity<TextInput>| {
+            div()
+                .bg(white())
+                .border_1()
+                .border_color(rgb(0x3311ff))
+                .child(input.clone())
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .p_1()
+            .bg(rgb(0xE8E8E8))
+            .rounded_sm()
+            .child(input(&editor.name))
+            .child(input(&editor.query))
+            .child(input(&editor.root))
+            .when_some(editor.error.clone(), |this, err| {
+                this.child(div().text_xs().text_color(red()).child(err))
+            })
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .gap_1()
+                    .child(button("Save", {
+                        let entity = cx.entity();
+                        move |_, cx| entity.update(cx, |this, cx| this.commit_edit(cx))
+                    }))
+                    .child(button("Cancel", {
+                        let entity = cx.entity();
+                        move |_, cx| {
+                            entity.update(cx, |this, cx| {
+                                this.editing = None;
+                                cx.notify();
+                            })
+                        }
+                    })),
+            )
+    }
+}
+
+impl Render for Sidebar {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .id("sidebar")
+            .flex()
+            .flex_col()
+            .flex_none()
+            .gap_1()
+            .w(px(200.))
+            .h_full()
+            .p_2()
+            .overflow_y_scroll()
+            .bg(rgb(0xF5F5F5))
+            .text_sm()
+            .border_r_1()
+            .border_color(rgb(0xE0E0E0))
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(rgb(0x555555))
+                    .child("SMART FOLDERS"),
+            )
+            .when(self.smart_folders.is_empty(), |this| {
+                this.child(
+                    div()
+                        .text_xs()
+                        .text_color(rgb(0x999999))
+                        .child("Save a search to keep it here"),
+                )
+            })
+            .children(
+                self.smart_folders
+                    .iter()
+                    .enumerate()
+                    .map(|(ix, smart_folder)| {
+                        let entity = cx.entity();
+                        // Dropping onto a smart folder transfers into the folder it searches
+                        let on_drop = self
+                            .data_table
+                            .read(cx)
+                            .drop_into(smart_folder.root.clone());
+                        div()
+                            .id(("smart-folder", ix))
+                            .flex()
+                            .flex_col()
+                            .child(
+                                drop_target(div().id("open"), on_drop)
+                                    .flex()
+                                    .flex_row()
+                                    .items_center()
+                                    .gap_1()
+                                    .px_1()
+                                    .rounded_sm()
+                                    .cursor_pointer()
+                                    .hover(|this| this.bg(rgb(0xE0E0E0)))
+                                    .on_click({
+                                        let entity = entity.clone();
+                                        move |_, _, cx| {
+                                            entity.update(cx, |this, cx| this.open(ix, cx))
+                                        }
+                                    })
+                                    .child(
+                                        div().flex_1().truncate().child(smart_folder.name.clone()),
+                                    )
+                                    .child(button("Edit", {
+                                        let entity = entity.clone();
+                                        move |_, cx| {
+                                            // Don't open it as well
+                                            cx.stop_propagation();
+                                            entity.update(cx, |this, cx| this.edit(ix, cx))
+                                        }
+                                    }))
+                                    .child(button("Remove", {
+                                        let entity = entity.clone();
+                                        move |_, cx| {
+                                            cx.stop_propagation();
+                                            entity.update(cx, |this, cx| this.remove(ix, cx))
+                                        }
+                                    })),
+                            )
+                            .when_some(
+                                self.editing.as_ref().filter(|editor| editor.ix == ix),
+                                |this, editor| this.child(self.render_editor(editor, cx)),
+                            )
+                    }),
+            )
+    }
+}
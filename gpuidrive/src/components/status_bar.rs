@@ -0,0 +1,92 @@
+use gpui::{prelude::FluentBuilder, *};
+
+use crate::jobs::Jobs;
+
+use super::button;
+
+/// Shows the progress of background jobs at the bottom of the window.
+pub struct StatusBar {
+    jobs: Entity<Jobs>,
+}
+
+impl StatusBar {
+    pub fn init(jobs: Entity<Jobs>) -> Self {
+        Self { jobs }
+    }
+}
+
+impl Render for StatusBar {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let jobs = self.jobs.read(cx).jobs();
+
+        div()
+            .flex()
+            .flex_col()
+            .w_full()
+            .bg(rgb(0xF0F0F0))
+            .text_xs()
+            .text_color(rgb(0x555555))
+            .children(jobs.iter().map(|job| {
+                let id = job.id;
+                let progress = if job.total == 0 {
+                    1.
+                } else {
+                    job.done as f32 / job.total as f32
+                };
+
+                div()
+                    .id(("job", id))
+                    .flex()
+                    .flex_col()
+                    .px_2()
+                    .py_1()
+                    .border_t_1()
+                    .border_color(rgb(0xE0E0E0))
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .items_center()
+                            .gap_2()
+                            .child(div().flex_1().truncate().child(job.label.clone()))
+                            // Nothing is known about the progress of errors which were only reported
+                            .when(job.total > 0 || !job.finished, |this| {
+                                this.child(format!("{} of {}", job.done, job.total)).child(
+                                    div()
+                                        .w(px(120.))
+                                        .h(px(6.))
+                                        .rounded_sm()
+                                        .bg(rgb(0xE0E0E0))
+                                        .child(
+                                            div()
+                                                .h_full()
+                                                .w(relative(progress))
+                                                .rounded_sm()
+                                                .bg(rgb(0x3311ff)),
+                                        ),
+                                )
+                            })
+                            .map(|this| {
+                                let jobs = self.jobs.clone();
+                                if job.finished {
+                                    this.child(button("Dismiss", move |_, cx| {
+                                        jobs.update(cx, |jobs, cx| jobs.dismiss(cx, id))
+                                    }))
+                                } else {
+                                    this.child(button("Cancel", move |_, cx| {
+                                        jobs.update(cx, |jobs, cx| jobs.cancel(cx, id))
+                                    }))
+                                }
+                            }),
+                    )
+                    .when(job.is_cancelled() && !job.finished, |this| {
+                        this.child("Cancelling...")
+                    })
+                    .children(
+                        job.errors
+                            .iter()
+                            .map(|err| div().text_color(red()).child(err.clone())),
+                    )
+            }))
+    }
+}
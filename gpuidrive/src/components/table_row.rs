@@ -1,17 +1,35 @@
-use std::rc::Rc;
+use std::{path::PathBuf, rc::Rc};
 
 use gpui::{prelude::FluentBuilder, *};
 use human_bytes::human_bytes;
 use opener::open;
 
-use crate::state::{Node, NodeKind, State};
+use crate::{
+    archive,
+    state::{Node, NodeKind, State},
+    transfer::TransferKind,
+};
+
+use super::{DraggedNodes, drag_kind};
+
+/// Called with the paths dropped onto a row and whether they should be copied or moved.
+pub type OnDrop = Rc<dyn Fn(Vec<PathBuf>, TransferKind, &mut Window, &mut App)>;
 
 #[derive(IntoElement)]
 pub struct TableRow {
     ix: usize,
     node: Rc<Node>,
     selected: bool,
+    /// The byte offsets of the characters in the name to highlight.
+    highlights: Vec<usize>,
+    /// Where a search result was found, shown in its own column.
+    relative_path: Option<SharedString>,
+    /// Replaces the name cell while the row is being renamed.
+    rename: Option<AnyElement>,
+    drag: Option<DraggedNodes>,
+    on_drop: Option<OnDrop>,
     on_click: Option<Box<dyn Fn(&ClickEvent, &mut Window, &mut App) + 'static>>,
+    on_right_click: Option<Box<dyn Fn(&MouseDownEvent, &mut Window, &mut App) + 'static>>,
 }
 
 impl TableRow {
@@ -20,7 +38,13 @@ impl TableRow {
             ix,
             node,
             selected,
+            highlights: Vec::new(),
+            relative_path: None,
+            rename: None,
+            drag: None,
+            on_drop: None,
             on_click: None,
+            on_right_click: None,
         }
     }
 
@@ -29,10 +53,74 @@ impl TableRow {
         self
     }
 
+    pub fn on_right_click(
+        mut self,
+        f: impl Fn(&MouseDownEvent, &mut Window, &mut App) + 'static,
+    ) -> Self {
+        self.on_right_click = Some(Box::new(f));
+        self
+    }
+
+    pub fn highlights(mut self, highlights: Vec<usize>) -> Self {
+        self.highlights = highlights;
+        self
+    }
+
+    pub fn relative_path(mut self, relative_path: impl Into<SharedString>) -> Self {
+        self.relative_path = Some(relative_path.into());
+        self
+    }
+
+    pub fn rename(mut self, input: impl IntoElement) -> Self {
+        self.rename = Some(input.into_any_element());
+        self
+    }
+
+    /// The nodes which are dragged when the user drags this row.
+    pub fn on_drag(mut self, drag: DraggedNodes) -> Self {
+        self.drag = Some(drag);
+        self
+    }
+
+    /// Accept nodes dragged from the table or files dragged from other applications.
+    pub fn on_drop(mut self, on_drop: OnDrop) -> Self {
+        self.on_drop = Some(on_drop);
+        self
+    }
+
+    fn render_name(&self, window: &Window) -> AnyElement {
+        // TODO: Handle non-utf8 file names
+        let name = self.node.name.to_string_lossy().to_string();
+        if self.highlights.is_empty() {
+            return name.into_any_element();
+        }
+
+        let highlights = self
+            .highlights
+            .iter()
+            .filter_map(|&start| {
+                let len = name.get(start..)?.chars().next()?.len_utf8();
+                Some((
+                    start..start + len,
+                    HighlightStyle {
+                        font_weight: Some(FontWeight::BOLD),
+                        background_color: Some(rgba(0xffe06680).into()),
+                        ..Default::default()
+                    },
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        StyledText::new(name)
+            .with_default_highlights(&window.text_style(), highlights)
+            .into_any_element()
+    }
+
     fn render_cell(
         &self,
         key: &str,
         width: DefiniteLength,
+        window: &Window,
         cx: &mut App,
     ) -> impl IntoElement + use<> {
         div()
@@ -41,7 +129,8 @@ impl TableRow {
             .w(width)
             .px_1()
             .child(match key {
-                "name" => div().child(self.node.name.to_string_lossy().to_string()),
+                "name" => div().child(self.render_name(window)),
+                "path" => div().child(self.relative_path.clone().unwrap_or_default()),
                 "kind" => div().child(format!("{:?}", self.node.kind)),
                 "size" => div().child(human_bytes(self.node.size as f64)), // TODO: This cast is bad
                 "created" => div().child(self.node.created.format("%B %d, %Y").to_string()),
@@ -59,8 +148,24 @@ const FIELDS: [(&str, f32); 5] = [
     ("modified", 0.09),
 ];
 
+/// Search results share the name column with where they were found.
+const SEARCH_FIELDS: [(&str, f32); 6] = [
+    ("name", 0.3),
+    ("path", 0.4),
+    ("kind", 0.07),
+    ("size", 0.05),
+    ("created", 0.09),
+    ("modified", 0.09),
+];
+
+fn fields(searching: bool) -> &'static [(&'static str, f32)] {
+    if searching { &SEARCH_FIELDS } else { &FIELDS }
+}
+
 impl RenderOnce for TableRow {
-    fn render(self, _window: &mut Window, cx: &mut App) -> impl IntoElement {
+    fn render(mut self, window: &mut Window, cx: &mut App) -> impl IntoElement {
+        let mut rename = self.rename.take();
+
         div()
             .id(self.ix) // TODO: Should this be scoped to `TableRow` component instance??
             .flex()
@@ -77,20 +182,74 @@ impl RenderOnce for TableRow {
             .py_0p5()
             .px_2()
             .w_full()
-            .children(FIELDS.map(|(key, width)| self.render_cell(key, relative(width), cx)))
+            .when(self.node.hidden, |this| this.text_color(rgb(0x999999)))
+            .children(
+                fields(self.relative_path.is_some())
+                    .iter()
+                    .map(|&(key, width)| {
+                        let rename = if key == "name" { rename.take() } else { None };
+                        match rename {
+                            Some(rename) => div()
+                                .w(relative(width))
+                                .px_1()
+                                .child(rename)
+                                .into_any_element(),
+                            None => self
+                                .render_cell(key, relative(width), window, cx)
+                                .into_any_element(),
+                        }
+                    }),
+            )
             .when_some(self.on_click, move |this, on_click| {
                 this.cursor_pointer().on_click(on_click)
             })
+            .when_some(self.on_right_click, |this, on_right_click| {
+                this.on_mouse_down(MouseButton::Right, on_right_click)
+            })
+            .when_some(self.drag, |this, drag| {
+                this.on_drag(drag, |drag, _, _, cx| cx.new(|_| drag.clone()))
+            })
+            .when_some(self.on_drop, drop_target)
     }
 }
 
+/// Highlight `element` while nodes or files from other applications are dragged over it and call `on_drop` with them.
+pub fn drop_target<E: InteractiveElement>(element: E, on_drop: OnDrop) -> E {
+    element
+        .drag_over::<DraggedNodes>(|style, _, _, _| style.bg(rgb(0xcce0ff)))
+        .drag_over::<ExternalPaths>(|style, _, _, _| style.bg(rgb(0xcce0ff)))
+        .on_drop({
+            let on_drop = on_drop.clone();
+            move |drag: &DraggedNodes, window, cx| {
+                on_drop(drag.paths.clone(), drag_kind(window), window, cx)
+            }
+        })
+        .on_drop(move |paths: &ExternalPaths, window, cx| {
+            on_drop(paths.paths().to_vec(), TransferKind::Copy, window, cx)
+        })
+}
+
 pub fn open_node(state: &Entity<State>, cx: &mut App, node: &Node, force: bool) {
+    // Opening a search result shows it in the directory it was found in
+    if !force && state.read(cx).searching().is_some() {
+        let path = node.path.clone();
+        state.update(cx, move |state, cx| state.reveal(cx, path));
+        return;
+    }
+
     match node.kind {
         NodeKind::Directory if !force => {
             let path = node.path.clone();
 
             state.update(cx, move |state: &mut State, cx| state.set_path(cx, path));
         }
+        // TODO: Extract to a temporary directory so files inside archives can be opened
+        NodeKind::File | NodeKind::Directory if state.read(cx).read_only() => {}
+        // Archives are browsed like directories
+        NodeKind::File if !force && archive::Format::detect(&node.path).is_some() => {
+            let path = node.path.clone();
+            state.update(cx, move |state, cx| state.set_path(cx, path));
+        }
         NodeKind::File | NodeKind::Directory => {
             open(node.path.clone()).unwrap();
         }
@@ -98,7 +257,7 @@ pub fn open_node(state: &Entity<State>, cx: &mut App, node: &Node, force: bool)
     }
 }
 
-pub fn render_titles() -> impl IntoElement {
+pub fn render_titles(searching: bool) -> impl IntoElement {
     div()
         .flex()
         .flex_row()
@@ -111,7 +270,7 @@ pub fn render_titles() -> impl IntoElement {
         .py_1()
         .px_2()
         .text_xs()
-        .children(FIELDS.map(|(key, width)| {
+        .children(fields(searching).iter().map(|&(key, width)| {
             div()
                 .whitespace_nowrap()
                 .flex_shrink_0()
@@ -5,7 +5,7 @@ use gpui::{
     EntityInputHandler, EventEmitter, FocusHandle, Focusable, GlobalElementId, LayoutId,
     MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, PaintQuad, Pixels, Point,
     ShapedLine, SharedString, Style, TextRun, UTF16Selection, UnderlineStyle, Window, actions, div,
-    fill, hsla, point, prelude::*, px, relative, rgba, size, white,
+    fill, hsla, point, prelude::*, px, red, relative, rgba, size, white,
 };
 use unicode_segmentation::*;
 
@@ -35,12 +35,47 @@ pub struct TextInput {
     pub selected_range: Range<usize>,
     pub selection_reversed: bool,
     pub marked_range: Option<Range<usize>>,
+    /// Part of the content to underline as invalid.
+    pub error_range: Option<Range<usize>>,
     pub last_layout: Option<ShapedLine>,
     pub last_bounds: Option<Bounds<Pixels>>,
     pub is_selecting: bool,
 }
 
 impl TextInput {
+    pub fn new(
+        cx: &mut Context<Self>,
+        content: impl Into<SharedString>,
+        placeholder: impl Into<SharedString>,
+    ) -> Self {
+        Self {
+            focus_handle: cx.focus_handle(),
+            content: content.into(),
+            placeholder: placeholder.into(),
+            selected_range: 0..0,
+            selection_reversed: false,
+            marked_range: None,
+            error_range: None,
+            last_layout: None,
+            last_bounds: None,
+            is_selecting: false,
+        }
+    }
+
+    /// Replace the content and move the cursor to the end. This doesn't emit `OnChange`.
+    pub fn set_content(&mut self, content: impl Into<SharedString>, cx: &mut Context<Self>) {
+        self.content = content.into();
+        self.selected_range = self.content.len()..self.content.len();
+        self.marked_range = None;
+        self.error_range = None;
+        cx.notify();
+    }
+
+    pub fn set_error_range(&mut self, range: Option<Range<usize>>, cx: &mut Context<Self>) {
+        self.error_range = range;
+        cx.notify();
+    }
+
     fn left(&mut self, _: &Left, _: &mut Window, cx: &mut Context<Self>) {
         if self.selected_range.is_empty() {
             self.move_to(self.previous_boundary(self.cursor_offset()), cx);
@@ -447,6 +482,10 @@ impl Element for TextElement {
             underline: None,
             strikethrough: None,
         };
+        let error_range = input
+            .error_range
+            .as_ref()
+            .filter(|range| !content.is_empty() && range.end <= content.len());
         let runs = if let Some(marked_range) = input.marked_range.as_ref() {
             vec![
                 TextRun {
@@ -470,6 +509,29 @@ impl Element for TextElement {
             .into_iter()
             .filter(|run| run.len > 0)
             .collect()
+        } else if let Some(error_range) = error_range {
+            vec![
+                TextRun {
+                    len: error_range.start,
+                    ..run.clone()
+                },
+                TextRun {
+                    len: error_range.end - error_range.start,
+                    underline: Some(UnderlineStyle {
+                        color: Some(red()),
+                        thickness: px(1.0),
+                        wavy: true,
+                    }),
+                    ..run.clone()
+                },
+                TextRun {
+                    len: display_text.len() - error_range.end,
+                    ..run.clone()
+                },
+            ]
+            .into_iter()
+            .filter(|run| run.len > 0)
+            .collect()
         } else {
             vec![run]
         };
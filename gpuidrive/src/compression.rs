@@ -0,0 +1,182 @@
+use std::{io, path::PathBuf, time::Duration};
+
+use futures::{
+    StreamExt,
+    channel::mpsc::{self, UnboundedSender},
+};
+use gpui::{App, AppContext, AsyncApp, Entity};
+
+use crate::{
+    archive::{self, Format, Level},
+    jobs::{JobId, Jobs},
+    state::State,
+};
+
+/// How often the progress of a job is updated.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+enum Update {
+    Progress(usize, usize),
+    Error(String),
+}
+
+/// Create an archive at `destination` containing `sources` as a background job.
+pub fn compress(
+    cx: &mut App,
+    jobs: Entity<Jobs>,
+    state: Entity<State>,
+    sources: Vec<PathBuf>,
+    destination: PathBuf,
+    format: Format,
+    level: Level,
+) {
+    if sources.is_empty() {
+        return;
+    }
+
+    let (id, cancelled) = jobs.update(cx, |jobs, cx| {
+        jobs.start(
+            cx,
+            format!(
+                "Compressing {} items to {}",
+                sources.len(),
+                destination.display()
+            ),
+            0,
+        )
+    });
+
+    cx.spawn(async move |cx| {
+        let result = run(cx, &jobs, id, {
+            let destination = destination.clone();
+            move |updates| {
+                archive::compress(
+                    &sources,
+                    &destination,
+                    format,
+                    level,
+                    &cancelled,
+                    |done, total| {
+                        updates.unbounded_send(Update::Progress(done, total)).ok();
+                    },
+                )
+            }
+        })
+        .await;
+
+        jobs.update(cx, |jobs, cx| {
+            match result {
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => jobs.error(cx, id, format!("{}: {err}", destination.display())),
+                Ok(()) => {}
+            }
+            jobs.finish(cx, id);
+        })
+        .ok();
+        state
+            .update(cx, |state, cx| {
+                if Some(state.path().as_path()) == destination.parent() {
+                    state.reload(cx);
+                    state.select_paths(cx, &[destination]);
+                }
+            })
+            .ok();
+    })
+    .detach();
+}
+
+/// Extract the archive at `path` into the folder `destination` as a background job.
+pub fn extract(
+    cx: &mut App,
+    jobs: Entity<Jobs>,
+    state: Entity<State>,
+    path: PathBuf,
+    destination: PathBuf,
+) {
+    let (id, cancelled) = jobs.update(cx, |jobs, cx| {
+        jobs.start(
+            cx,
+            format!(
+                "Extracting {} to {}",
+                path.file_name().unwrap_or_default().to_string_lossy(),
+                destination.display()
+            ),
+            0,
+        )
+    });
+
+    cx.spawn(async move |cx| {
+        let result = run(cx, &jobs, id, {
+            let (path, destination) = (path.clone(), destination.clone());
+            move |updates| {
+                archive::extract(
+                    &path,
+                    &destination,
+                    &cancelled,
+                    |done, total| {
+                        updates.unbounded_send(Update::Progress(done, total)).ok();
+                    },
+                    |entry, err| {
+                        updates
+                            .unbounded_send(Update::Error(format!("{}: {err}", entry.display())))
+                            .ok();
+                    },
+                )
+            }
+        })
+        .await;
+
+        jobs.update(cx, |jobs, cx| {
+            match result {
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => jobs.error(cx, id, format!("{}: {err}", path.display())),
+                Ok(()) => {}
+            }
+            jobs.finish(cx, id);
+        })
+        .ok();
+        state
+            .update(cx, |state, cx| {
+                if state.path() == &destination {
+                    state.reload(cx);
+                } else if Some(state.path().as_path()) == destination.parent() {
+                    state.reload(cx);
+                    state.select_paths(cx, &[destination]);
+                }
+            })
+            .ok();
+    })
+    .detach();
+}
+
+/// Run `work` in the background, showing the progress and errors it reports on the job `id`.
+async fn run<T: Send + 'static>(
+    cx: &mut AsyncApp,
+    jobs: &Entity<Jobs>,
+    id: JobId,
+    work: impl FnOnce(UnboundedSender<Update>) -> T + Send + 'static,
+) -> T {
+    let (tx, mut rx) = mpsc::unbounded();
+    let task = cx.background_executor().spawn(async move { work(tx) });
+
+    // This ends once `work` is done and has dropped the sender
+    while let Some(update) = rx.next().await {
+        let mut updates = vec![update];
+        while let Ok(Some(update)) = rx.try_next() {
+            updates.push(update);
+        }
+
+        jobs.update(cx, |jobs, cx| {
+            for update in updates {
+                match update {
+                    Update::Progress(done, total) => jobs.progress(cx, id, done, total),
+                    Update::Error(err) => jobs.error(cx, id, err),
+                }
+            }
+        })
+        .ok();
+        cx.background_executor().timer(UPDATE_INTERVAL).await;
+    }
+
+    task.await
+}
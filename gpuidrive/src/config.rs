@@ -0,0 +1,93 @@
+//! User preferences which persist between launches.
+
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{
+        LazyLock,
+        mpsc::{self, Sender},
+    },
+    thread,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::filter::FilterMode;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub show_hidden: bool,
+    pub smart_folders: Vec<SmartFolder>,
+}
+
+/// A saved search which is shown in the sidebar.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartFolder {
+    pub name: String,
+    /// The directory the search starts in.
+    pub root: PathBuf,
+    pub query: String,
+    #[serde(default)]
+    pub mode: FilterMode,
+}
+
+type Update = Box<dyn FnOnce(&mut Config) + Send>;
+
+/// Updates are saved one after another on their own thread, so the UI doesn't wait for the disk
+/// and a later update can't be overwritten by an earlier one.
+static UPDATES: LazyLock<Sender<Update>> = LazyLock::new(|| {
+    let (tx, rx) = mpsc::channel::<Update>();
+    thread::spawn(move || {
+        for f in rx {
+            Config::save(f);
+        }
+    });
+    tx
+});
+
+/// The directory all of our persistent files live in, `~/.config/gpuidrive` on Linux.
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("gpuidrive"))
+}
+
+impl Config {
+    fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("config.json"))
+    }
+
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            // TODO: Tell the user if their config is invalid instead of silently resetting it
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Load the config, apply `f` and save it back in the background.
+    pub fn update(f: impl FnOnce(&mut Self) + Send + 'static) {
+        UPDATES.send(Box::new(f)).ok();
+    }
+
+    fn save(f: Update) {
+        let mut config = Self::load();
+        f(&mut config);
+
+        let Some(path) = Self::path() else {
+            return;
+        };
+        let result = path
+            .parent()
+            .map(fs::create_dir_all)
+            .transpose()
+            .and_then(|_| {
+                fs::write(
+                    &path,
+                    serde_json::to_string_pretty(&config).expect("config is serializable"),
+                )
+            });
+        if let Err(err) = result {
+            log::warn!("Failed to save config to {}: {err}", path.display());
+        }
+    }
+}
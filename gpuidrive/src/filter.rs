@@ -0,0 +1,156 @@
+//! Narrowing the listing down to the names matching a pattern.
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FilterMode {
+    /// The name contains the query.
+    #[default]
+    Substring,
+    /// The whole name matches a shell glob (`*`, `?` and `[...]`).
+    Glob,
+    /// The characters of the query appear in the name in order.
+    Fuzzy,
+}
+
+impl FilterMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Substring => Self::Glob,
+            Self::Glob => Self::Fuzzy,
+            Self::Fuzzy => Self::Substring,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Substring => "Contains",
+            Self::Glob => "Glob",
+            Self::Fuzzy => "Fuzzy",
+        }
+    }
+}
+
+/// A case-insensitive filter over file names.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    query: String,
+    mode: FilterMode,
+    /// Globs are compiled to a regex where each literal run is a capture group so it can be highlighted.
+    glob: Option<Regex>,
+}
+
+impl Filter {
+    pub fn new(query: impl Into<String>, mode: FilterMode) -> Self {
+        let query = query.into();
+        Self {
+            glob: if mode == FilterMode::Glob {
+                glob_to_regex(&query)
+            } else {
+                None
+            },
+            query,
+            mode,
+        }
+    }
+
+    /// If `name` matches, the byte offsets of the matched characters for highlighting.
+    pub fn matches(&self, name: &str) -> Option<Vec<usize>> {
+        if self.query.is_empty() {
+            return Some(Vec::new());
+        }
+
+        match self.mode {
+            FilterMode::Substring => substring(name, &self.query),
+            FilterMode::Glob => {
+                let captures = self.glob.as_ref()?.captures(name)?;
+                Some(
+                    captures
+                        .iter()
+                        .skip(1)
+                        .flatten()
+                        .flat_map(|m| {
+                            name[m.range()]
+                                .char_indices()
+                                .map(move |(i, _)| m.start() + i)
+                        })
+                        .collect(),
+                )
+            }
+            FilterMode::Fuzzy => {
+                crate::fuzzy::fuzzy_match(name, &self.query).map(|(_, positions)| positions)
+            }
+        }
+    }
+}
+
+fn eq_ignore_case(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+fn substring(name: &str, query: &str) -> Option<Vec<usize>> {
+    let query = query.chars().collect::<Vec<_>>();
+    let chars = name.char_indices().collect::<Vec<_>>();
+
+    chars
+        .windows(query.len())
+        .find(|window| {
+            window
+                .iter()
+                .zip(&query)
+                .all(|((_, a), b)| eq_ignore_case(*a, *b))
+        })
+        .map(|window| window.iter().map(|(i, _)| *i).collect())
+}
+
+/// Returns `None` if the glob is invalid, eg. an unclosed `[`.
+fn glob_to_regex(glob: &str) -> Option<Regex> {
+    let mut pattern = String::from("^");
+    let mut literal = String::new();
+    let flush = |pattern: &mut String, literal: &mut String| {
+        if !literal.is_empty() {
+            pattern.push('(');
+            pattern.push_str(&regex::escape(literal));
+            pattern.push(')');
+            literal.clear();
+        }
+    };
+
+    let mut chars = glob.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => {
+                flush(&mut pattern, &mut literal);
+                pattern.push_str(".*");
+            }
+            '?' => {
+                flush(&mut pattern, &mut literal);
+                pattern.push('.');
+            }
+            '[' => {
+                flush(&mut pattern, &mut literal);
+                pattern.push('[');
+                let mut first = true;
+                loop {
+                    match chars.next()? {
+                        ']' if !first => break,
+                        '!' if first => pattern.push('^'),
+                        '\\' => pattern.push_str("\\\\"),
+                        c => pattern.push(c),
+                    }
+                    first = false;
+                }
+                pattern.push(']');
+            }
+            c => literal.push(c),
+        }
+    }
+    flush(&mut pattern, &mut literal);
+    pattern.push('$');
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(true)
+        .build()
+        .ok()
+}
@@ -0,0 +1,129 @@
+//! Remembering which directories are visited, ranked by how often and how recently, like zoxide.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::config_dir;
+
+/// Once the ranks add up to more than this every rank is scaled down, forgetting old visits.
+const MAX_TOTAL_RANK: f64 = 10_000.;
+/// Entries whose rank decays below this are dropped.
+const MIN_RANK: f64 = 1.;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// Visits are recorded in the background so make sure they don't overwrite each other.
+static LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub path: PathBuf,
+    /// Roughly the number of visits.
+    pub rank: f64,
+    /// Seconds since the Unix epoch.
+    pub last_visited: u64,
+}
+
+impl Entry {
+    /// The rank weighted by how recently the directory was visited.
+    pub fn score(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last_visited);
+        self.rank
+            * if age < HOUR {
+                4.
+            } else if age < DAY {
+                2.
+            } else if age < WEEK {
+                0.5
+            } else {
+                0.25
+            }
+    }
+}
+
+fn database_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("frecency.json"))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Every directory which still exists, best first.
+pub fn load() -> Vec<Entry> {
+    let now = now();
+    let mut entries = read()
+        .into_iter()
+        .filter(|entry| entry.path.is_dir())
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| b.score(now).total_cmp(&a.score(now)));
+    entries
+}
+
+fn read() -> Vec<Entry> {
+    database_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        // TODO: Tell the user if the database is corrupt instead of silently resetting it
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Record a visit to `path`. This does IO so should be run in the background.
+pub fn visit(path: &Path) {
+    // eg. a directory inside an archive
+    if !path.is_dir() {
+        return;
+    }
+    let _lock = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let mut entries = read();
+    entries.retain(|entry| entry.path.is_dir());
+
+    let now = now();
+    match entries.iter_mut().find(|entry| entry.path == path) {
+        Some(entry) => {
+            entry.rank += 1.;
+            entry.last_visited = now;
+        }
+        None => entries.push(Entry {
+            path: path.to_path_buf(),
+            rank: 1.,
+            last_visited: now,
+        }),
+    }
+
+    let total = entries.iter().map(|entry| entry.rank).sum::<f64>();
+    if total > MAX_TOTAL_RANK {
+        let factor = 0.9 * MAX_TOTAL_RANK / total;
+        for entry in &mut entries {
+            entry.rank *= factor;
+        }
+        entries.retain(|entry| entry.rank >= MIN_RANK);
+    }
+
+    let Some(path) = database_path() else {
+        return;
+    };
+    let result = path
+        .parent()
+        .map(fs::create_dir_all)
+        .transpose()
+        .and_then(|_| {
+            fs::write(
+                &path,
+                serde_json::to_string(&entries).expect("entries are serializable"),
+            )
+        });
+    if let Err(err) = result {
+        log::warn!("Failed to save {}: {err}", path.display());
+    }
+}
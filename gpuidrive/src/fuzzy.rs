@@ -0,0 +1,168 @@
+//! Ranking candidates by how well they fuzzy match a query, for the pickers.
+
+/// A matched character.
+const MATCH_SCORE: i64 = 16;
+/// A matched character right after the previous one.
+const CONSECUTIVE_BONUS: i64 = 24;
+/// A matched character at the start of a word, eg. after a `/` or `_` or a lowercase letter.
+const BOUNDARY_BONUS: i64 = 20;
+/// A matched character in the last path component, so file names beat directory names.
+const BASENAME_BONUS: i64 = 8;
+
+/// Match `query` against `candidate` case-insensitively.
+///
+/// Every whitespace separated word of the query has to appear in order, with the characters
+/// of each word in order. Returns the score, higher is better, and the byte offsets of the
+/// matched characters.
+pub fn fuzzy_match(candidate: &str, query: &str) -> Option<(i64, Vec<usize>)> {
+    let chars = candidate.char_indices().collect::<Vec<_>>();
+    let basename = chars
+        .iter()
+        .rposition(|(_, c)| *c == '/')
+        .map_or(0, |ix| ix + 1);
+
+    let mut from = 0;
+    let mut score = 0;
+    let mut positions = Vec::new();
+    for word in query.split_whitespace() {
+        let word = word.chars().collect::<Vec<_>>();
+        let (word_score, matched) = match_word(&chars, &word, from, basename)?;
+        from = matched.last().map_or(from, |ix| ix + 1);
+        score += word_score;
+        positions.extend(matched.into_iter().map(|ix| chars[ix].0));
+    }
+
+    // Prefer shorter candidates when everything else is equal
+    Some((score - chars.len() as i64 / 4, positions))
+}
+
+fn eq_ignore_case(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+/// The best scoring match of `word` in `chars[from..]`, as indexes into `chars`.
+fn match_word(
+    chars: &[(usize, char)],
+    word: &[char],
+    from: usize,
+    basename: usize,
+) -> Option<(i64, Vec<usize>)> {
+    let (first, rest) = word.split_first()?;
+
+    let mut best = None::<(i64, Vec<usize>)>;
+    for start in from..chars.len() {
+        if !eq_ignore_case(chars[start].1, *first) {
+            continue;
+        }
+
+        // Greedily match the rest of the word after this start
+        let mut positions = vec![start];
+        let mut ix = start + 1;
+        for q in rest {
+            while ix < chars.len() && !eq_ignore_case(chars[ix].1, *q) {
+                ix += 1;
+            }
+            if ix == chars.len() {
+                // If it doesn't fit after this start it won't after a later one either
+                return best;
+            }
+            positions.push(ix);
+            ix += 1;
+        }
+
+        let score = score(chars, &positions, basename);
+        if best.as_ref().is_none_or(|(best, _)| score > *best) {
+            best = Some((score, positions));
+        }
+    }
+    best
+}
+
+fn score(chars: &[(usize, char)], positions: &[usize], basename: usize) -> i64 {
+    let mut score = 0;
+    let mut previous = None::<usize>;
+    for &ix in positions {
+        score += MATCH_SCORE;
+        match previous {
+            Some(previous) if previous + 1 == ix => score += CONSECUTIVE_BONUS,
+            // Penalise the gap since the previous match
+            Some(previous) => score -= (ix - previous - 1).min(16) as i64,
+            None => {}
+        }
+        if is_boundary(chars, ix) {
+            score += BOUNDARY_BONUS;
+        }
+        if ix >= basename {
+            score += BASENAME_BONUS;
+        }
+        previous = Some(ix);
+    }
+    score
+}
+
+fn is_boundary(chars: &[(usize, char)], ix: usize) -> bool {
+    let Some(previous) = ix.checked_sub(1).map(|ix| chars[ix].1) else {
+        return true;
+    };
+    let current = chars[ix].1;
+    matches!(previous, '/' | '\\' | '_' | '-' | '.' | ' ')
+        || (previous.is_lowercase() && current.is_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(candidate: &str, query: &str) -> Option<Vec<usize>> {
+        fuzzy_match(candidate, query).map(|(_, positions)| positions)
+    }
+
+    fn score(candidate: &str, query: &str) -> i64 {
+        fuzzy_match(candidate, query).unwrap().0
+    }
+
+    #[test]
+    fn matches_in_order_ignoring_case() {
+        assert_eq!(positions("main.rs", "mrs"), Some(vec![0, 5, 6]));
+        assert_eq!(positions("Main.RS", "mrs"), Some(vec![0, 5, 6]));
+        assert_eq!(positions("main.rs", "srm"), None);
+        assert_eq!(positions("main.rs", "x"), None);
+        assert_eq!(positions("main.rs", ""), Some(vec![]));
+    }
+
+    #[test]
+    fn words_match_in_order() {
+        assert_eq!(
+            positions("src/components/picker.rs", "comp pick"),
+            Some(vec![4, 5, 6, 7, 15, 16, 17, 18])
+        );
+        assert_eq!(positions("src/components/picker.rs", "pick comp"), None);
+    }
+
+    #[test]
+    fn positions_are_byte_offsets() {
+        assert_eq!(positions("café/menu", "fm"), Some(vec![2, 6]));
+    }
+
+    #[test]
+    fn ranking() {
+        // Consecutive characters beat scattered ones
+        assert!(score("picker.rs", "pick") > score("p_i_c_k.rs", "pick"));
+        // Word boundaries beat the middle of words
+        assert!(score("file_finder.rs", "ff") > score("offer.rs", "ff"));
+        assert!(score("FileFinder.rs", "ff") > score("offer.rs", "ff"));
+        // The file name beats a directory name
+        assert!(score("state/main.rs", "state") < score("main/state.rs", "state"));
+        // Shorter candidates win ties
+        assert!(score("state.rs", "state") > score("state_machine.rs", "state"));
+    }
+
+    #[test]
+    fn picks_the_best_start() {
+        // The later `pick` is consecutive and at a boundary
+        assert_eq!(
+            positions("p/i/c/k/picker.rs", "pick"),
+            Some(vec![8, 9, 10, 11])
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Searching the contents of files.
+
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::atomic::AtomicBool,
+};
+
+use regex::{Regex, RegexBuilder};
+
+/// Larger files are skipped, they are rarely text and would take too long to read.
+pub const MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+/// Like git, a file with a NUL byte near the start is treated as binary.
+const BINARY_SNIFF_LEN: usize = 8 * 1024;
+/// Long lines (eg. minified code) are cut down to the part around the first match.
+const MAX_SNIPPET_LEN: usize = 200;
+const SNIPPET_CONTEXT: usize = 40;
+
+#[derive(Debug, Clone)]
+pub struct LineMatch {
+    /// Zero-based.
+    pub line: usize,
+    pub snippet: String,
+    /// The byte ranges of the matches within `snippet`.
+    pub ranges: Vec<Range<usize>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileMatches {
+    pub path: PathBuf,
+    pub lines: Vec<LineMatch>,
+}
+
+/// Build the regex for a query. It's case-insensitive unless the query contains an uppercase letter.
+pub fn query(text: &str, is_regex: bool) -> Result<Regex, regex::Error> {
+    let pattern = if is_regex {
+        text.to_string()
+    } else {
+        regex::escape(text)
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!text.chars().any(char::is_uppercase))
+        .build()
+}
+
+/// Search every file below `root` in parallel, calling `f` for each file which matches.
+pub fn grep(
+    root: &Path,
+    regex: &Regex,
+    show_hidden: bool,
+    cancelled: &AtomicBool,
+    f: impl Fn(FileMatches) + Sync + Send,
+) {
+    indexer::walk(
+        root,
+        cancelled,
+        |entry| show_hidden || !entry.file_name().as_encoded_bytes().starts_with(b"."),
+        |entry| {
+            if !entry.file_type().is_file() {
+                return;
+            }
+            if let Some(matches) = search_file(entry.path(), regex) {
+                f(matches);
+            }
+        },
+    );
+}
+
+/// The matching lines in `path`, or `None` if there are none or it isn't a text file.
+pub fn search_file(path: &Path, regex: &Regex) -> Option<FileMatches> {
+    if fs::metadata(path).ok()?.len() > MAX_FILE_SIZE {
+        return None;
+    }
+    let content = fs::read(path).ok()?;
+    if is_binary(&content) {
+        return None;
+    }
+
+    let content = String::from_utf8_lossy(&content);
+    let lines = content
+        .lines()
+        .enumerate()
+        .filter_map(|(line, text)| {
+            let first = regex.find(text)?;
+            let start = char_boundary(text, first.start().saturating_sub(SNIPPET_CONTEXT));
+            let end = char_boundary(text, start + MAX_SNIPPET_LEN);
+
+            Some(LineMatch {
+                line,
+                snippet: text[start..end].to_string(),
+                ranges: regex
+                    .find_iter(text)
+                    .filter(|m| m.start() >= start && m.end() <= end && !m.is_empty())
+                    .map(|m| m.start() - start..m.end() - start)
+                    .collect(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    (!lines.is_empty()).then(|| FileMatches {
+        path: path.to_path_buf(),
+        lines,
+    })
+}
+
+/// Whether `content` looks like a binary file rather than text.
+pub fn is_binary(content: &[u8]) -> bool {
+    content[..content.len().min(BINARY_SNIFF_LEN)].contains(&0)
+}
+
+/// The closest char boundary at or before `ix`.
+fn char_boundary(text: &str, ix: usize) -> usize {
+    let mut ix = ix.min(text.len());
+    while !text.is_char_boundary(ix) {
+        ix -= 1;
+    }
+    ix
+}
@@ -0,0 +1,119 @@
+//! Hex dumps of binary files for the quick preview.
+
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    ops::Range,
+    path::Path,
+};
+
+pub const BYTES_PER_ROW: usize = 16;
+
+/// A known file format identified by the bytes at the start of the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Magic {
+    pub name: &'static str,
+    /// Where the identifying bytes are, eg. tar's `ustar` is 257 bytes in.
+    pub range: Range<u64>,
+}
+
+/// The offset, the identifying bytes and the name of each format we know of.
+const MAGIC_NUMBERS: &[(u64, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (0, b"\xFF\xD8\xFF", "JPEG image"),
+    (0, b"GIF87a", "GIF image"),
+    (0, b"GIF89a", "GIF image"),
+    (0, b"%PDF-", "PDF document"),
+    (0, b"PK\x03\x04", "ZIP archive"),
+    (0, b"\x1F\x8B", "gzip archive"),
+    (0, b"\xFD7zXZ\x00", "xz archive"),
+    (0, b"\x28\xB5\x2F\xFD", "Zstandard archive"),
+    (0, b"BZh", "bzip2 archive"),
+    (0, b"7z\xBC\xAF\x27\x1C", "7-Zip archive"),
+    (0, b"Rar!\x1A\x07", "RAR archive"),
+    (257, b"ustar", "tar archive"),
+    (0, b"\x7FELF", "ELF executable"),
+    (0, b"\xCF\xFA\xED\xFE", "Mach-O executable"),
+    (0, b"\xCA\xFE\xBA\xBE", "Mach-O universal binary"),
+    (0, b"MZ", "Windows executable"),
+    (0, b"\x00asm", "WebAssembly module"),
+    (0, b"SQLite format 3\x00", "SQLite database"),
+    (0, b"OggS", "Ogg media"),
+    (0, b"fLaC", "FLAC audio"),
+    (0, b"ID3", "MP3 audio"),
+    (0, b"RIFF", "RIFF media"),
+    (4, b"ftyp", "MPEG-4 media"),
+    (0, b"\x1A\x45\xDF\xA3", "Matroska media"),
+    (0, b"wOFF", "WOFF font"),
+    (0, b"wOF2", "WOFF2 font"),
+    (0, b"\x00\x01\x00\x00\x00", "TrueType font"),
+    (0, b"OTTO", "OpenType font"),
+];
+
+/// Identify the format of a file from its first bytes.
+pub fn detect_magic(header: &[u8]) -> Option<Magic> {
+    MAGIC_NUMBERS
+        .iter()
+        .find(|(offset, magic, _)| {
+            header
+                .get(*offset as usize..)
+                .is_some_and(|header| header.starts_with(magic))
+        })
+        .map(|(offset, magic, name)| Magic {
+            name,
+            range: *offset..offset + magic.len() as u64,
+        })
+}
+
+/// Read `len` bytes at `offset`, or fewer at the end of the file.
+pub fn read_window(path: &Path, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut bytes = Vec::with_capacity(len);
+    file.take(len as u64).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// The bytes of a row as hex pairs, with an extra space between the two halves.
+pub fn hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(BYTES_PER_ROW * 3 + 1);
+    for (ix, byte) in bytes.iter().enumerate() {
+        if ix > 0 {
+            hex.push(' ');
+        }
+        if ix == BYTES_PER_ROW / 2 {
+            hex.push(' ');
+        }
+        hex.push_str(&format!("{byte:02x}"));
+    }
+    hex
+}
+
+/// Where the bytes in `range` of a row are in its [`hex`] string.
+pub fn hex_range(range: Range<usize>) -> Range<usize> {
+    let column = |ix: usize| ix * 3 + usize::from(ix >= BYTES_PER_ROW / 2);
+    column(range.start)..column(range.end - 1) + 2
+}
+
+/// The bytes of a row as printable ASCII, with a `.` for anything else.
+pub fn ascii(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            }
+        })
+        .collect()
+}
+
+/// Parse a "go to offset" input: hex with a `0x` prefix, otherwise decimal.
+pub fn parse_offset(text: &str) -> Option<u64> {
+    let text = text.trim().replace('_', "");
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
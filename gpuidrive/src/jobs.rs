@@ -0,0 +1,108 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use gpui::Context;
+
+/// Long running operations (copying, moving, etc) which run in the background.
+///
+/// The work itself is done by whoever started the job, this only tracks progress so it can be shown to the user.
+#[derive(Default)]
+pub struct Jobs {
+    jobs: Vec<Job>,
+    next_id: usize,
+}
+
+pub type JobId = usize;
+
+pub struct Job {
+    pub id: JobId,
+    pub label: String,
+    pub done: usize,
+    pub total: usize,
+    pub errors: Vec<String>,
+    pub finished: bool,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Job {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl Jobs {
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    /// Start tracking a new job. The returned flag is set when the user cancels the job.
+    pub fn start(
+        &mut self,
+        cx: &mut Context<Self>,
+        label: String,
+        total: usize,
+    ) -> (JobId, Arc<AtomicBool>) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.jobs.push(Job {
+            id,
+            label,
+            done: 0,
+            total,
+            errors: Vec::new(),
+            finished: false,
+            cancelled: cancelled.clone(),
+        });
+        cx.notify();
+
+        (id, cancelled)
+    }
+
+    pub fn progress(&mut self, cx: &mut Context<Self>, id: JobId, done: usize, total: usize) {
+        if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+            job.done = done;
+            job.total = total;
+            cx.notify();
+        }
+    }
+
+    pub fn error(&mut self, cx: &mut Context<Self>, id: JobId, error: String) {
+        if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+            job.errors.push(error);
+            cx.notify();
+        }
+    }
+
+    /// Show an error from something which wasn't a job, eg. a single file operation, until it's dismissed.
+    pub fn report(&mut self, cx: &mut Context<Self>, label: String, error: String) {
+        let (id, _) = self.start(cx, label, 0);
+        self.error(cx, id, error);
+        self.finish(cx, id);
+    }
+
+    /// Mark the job as finished. Jobs with errors are kept around until they are dismissed.
+    pub fn finish(&mut self, cx: &mut Context<Self>, id: JobId) {
+        self.jobs
+            .retain(|job| job.id != id || !job.errors.is_empty());
+        if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+            job.finished = true;
+        }
+        cx.notify();
+    }
+
+    pub fn cancel(&mut self, cx: &mut Context<Self>, id: JobId) {
+        if let Some(job) = self.jobs.iter().find(|job| job.id == id) {
+            job.cancelled.store(true, Ordering::Relaxed);
+            cx.notify();
+        }
+    }
+
+    pub fn dismiss(&mut self, cx: &mut Context<Self>, id: JobId) {
+        self.jobs.retain(|job| job.id != id);
+        cx.notify();
+    }
+}
@@ -0,0 +1,245 @@
+//! The default key bindings and the user's `keymap.json` which overrides them.
+//!
+//! The keymap is a list of sections, each binding keys to actions for a key context:
+//!
+//! ```json
+//! [
+//!     {
+//!         "context": "DataTable && not_editing",
+//!         "bindings": {
+//!             "j": "gpuidrive::SelectNext",
+//!             "k": "gpuidrive::SelectPrevious",
+//!             "delete": null
+//!         }
+//!     }
+//! ]
+//! ```
+//!
+//! Binding a key to `null` removes the default binding.
+
+use std::{collections::BTreeMap, fs, path::PathBuf, rc::Rc, time::Duration};
+
+use gpui::*;
+use serde::Deserialize;
+
+use crate::{actions::*, components, config::config_dir};
+
+/// The list while it's focused and the user isn't renaming something.
+const LIST: Option<&str> = Some("DataTable && not_editing");
+const INPUT: Option<&str> = Some("TextInput");
+const PREVIEW: Option<&str> = Some("QuickPreview");
+const PICKER: Option<&str> = Some("Picker");
+
+/// How often to check if the keymap file has changed.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Deserialize)]
+struct KeymapSection {
+    #[serde(default)]
+    context: Option<String>,
+    bindings: BTreeMap<String, Option<String>>,
+}
+
+fn keymap_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("keymap.json"))
+}
+
+fn default_bindings() -> Vec<KeyBinding> {
+    let mut bindings = vec![
+        // List
+        KeyBinding::new("up", SelectPrevious, LIST),
+        KeyBinding::new("down", SelectNext, LIST),
+        KeyBinding::new("pageup", SelectPageUp, LIST),
+        KeyBinding::new("pagedown", SelectPageDown, LIST),
+        KeyBinding::new("home", SelectFirst, LIST),
+        KeyBinding::new("end", SelectLast, LIST),
+        KeyBinding::new("escape", ClearSelection, LIST),
+        KeyBinding::new("backspace", GoUp, LIST),
+        KeyBinding::new("enter", Rename, LIST),
+        KeyBinding::new("f2", Rename, LIST),
+        KeyBinding::new("space", ToggleQuickPreview, LIST),
+        KeyBinding::new("/", OpenFilter, LIST),
+        // Quick preview
+        KeyBinding::new("space", ToggleQuickPreview, PREVIEW),
+        KeyBinding::new("escape", ToggleQuickPreview, PREVIEW),
+        KeyBinding::new("up", SelectPrevious, PREVIEW),
+        KeyBinding::new("down", SelectNext, PREVIEW),
+        // Pickers
+        KeyBinding::new("up", SelectPrevious, PICKER),
+        KeyBinding::new("down", SelectNext, PICKER),
+        KeyBinding::new("enter", Confirm, PICKER),
+        KeyBinding::new("escape", Dismiss, PICKER),
+        // Input
+        KeyBinding::new("backspace", components::Backspace, INPUT),
+        KeyBinding::new("delete", components::Delete, INPUT),
+        KeyBinding::new("left", components::Left, INPUT),
+        KeyBinding::new("right", components::Right, INPUT),
+        KeyBinding::new("shift-left", components::SelectLeft, INPUT),
+        KeyBinding::new("shift-right", components::SelectRight, INPUT),
+        KeyBinding::new("home", components::Home, INPUT),
+        KeyBinding::new("end", components::End, INPUT),
+    ];
+
+    if cfg!(target_os = "macos") {
+        bindings.extend([
+            KeyBinding::new("cmd-q", QuitApp, None),
+            KeyBinding::new("cmd-w", CloseWindow, None),
+            KeyBinding::new("cmd-[", GoBack, LIST),
+            KeyBinding::new("cmd-]", GoForward, LIST),
+            KeyBinding::new("cmd-up", GoUp, LIST),
+            KeyBinding::new("cmd-down", OpenSelected, LIST),
+            KeyBinding::new("cmd-o", OpenSelectedExternally, LIST),
+            KeyBinding::new("cmd-shift-n", NewFolder, LIST),
+            KeyBinding::new("cmd-c", CopyFiles, LIST),
+            KeyBinding::new("cmd-x", CutFiles, LIST),
+            KeyBinding::new("cmd-v", PasteFiles, LIST),
+            KeyBinding::new("cmd-backspace", MoveToTrash, LIST),
+            KeyBinding::new("cmd-i", ToggleProperties, LIST),
+            KeyBinding::new("cmd-shift-.", ToggleHiddenFiles, LIST),
+            KeyBinding::new("cmd-f", OpenFilter, LIST),
+            KeyBinding::new("cmd-shift-f", SearchSubfolders, LIST),
+            KeyBinding::new("cmd-alt-f", SearchContents, LIST),
+            KeyBinding::new("cmd-p", FindFile, None),
+            KeyBinding::new("cmd-shift-p", ToggleCommandPalette, None),
+            KeyBinding::new("cmd-shift-g", JumpToFolder, None),
+            KeyBinding::new("cmd-enter", SecondaryConfirm, PICKER),
+            KeyBinding::new("cmd-a", components::SelectAll, INPUT),
+            KeyBinding::new("cmd-c", components::Copy, INPUT),
+            KeyBinding::new("cmd-x", components::Cut, INPUT),
+            KeyBinding::new("cmd-v", components::Paste, INPUT),
+            KeyBinding::new("ctrl-cmd-space", components::ShowCharacterPalette, INPUT),
+        ]);
+    } else {
+        bindings.extend([
+            KeyBinding::new("ctrl-q", QuitApp, None),
+            KeyBinding::new("ctrl-w", CloseWindow, None),
+            KeyBinding::new("alt-left", GoBack, LIST),
+            KeyBinding::new("alt-right", GoForward, LIST),
+            KeyBinding::new("alt-up", GoUp, LIST),
+            KeyBinding::new("alt-down", OpenSelected, LIST),
+            KeyBinding::new("ctrl-o", OpenSelectedExternally, LIST),
+            KeyBinding::new("ctrl-shift-n", NewFolder, LIST),
+            KeyBinding::new("ctrl-c", CopyFiles, LIST),
+            KeyBinding::new("ctrl-x", CutFiles, LIST),
+            KeyBinding::new("ctrl-v", PasteFiles, LIST),
+            KeyBinding::new("delete", MoveToTrash, LIST),
+            KeyBinding::new("ctrl-i", ToggleProperties, LIST),
+            KeyBinding::new("ctrl-h", ToggleHiddenFiles, LIST),
+            KeyBinding::new("ctrl-f", OpenFilter, LIST),
+            KeyBinding::new("ctrl-shift-f", SearchSubfolders, LIST),
+            KeyBinding::new("ctrl-alt-f", SearchContents, LIST),
+            KeyBinding::new("ctrl-p", FindFile, None),
+            KeyBinding::new("ctrl-shift-p", ToggleCommandPalette, None),
+            KeyBinding::new("ctrl-g", JumpToFolder, None),
+            KeyBinding::new("ctrl-enter", SecondaryConfirm, PICKER),
+            KeyBinding::new("ctrl-a", components::SelectAll, INPUT),
+            KeyBinding::new("ctrl-c", components::Copy, INPUT),
+            KeyBinding::new("ctrl-x", components::Cut, INPUT),
+            KeyBinding::new("ctrl-v", components::Paste, INPUT),
+        ]);
+    }
+
+    bindings
+}
+
+/// Parse the user's keymap, skipping any invalid bindings.
+fn user_bindings(cx: &App) -> Vec<KeyBinding> {
+    let Some(path) = keymap_path() else {
+        return Vec::new();
+    };
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    let sections = match serde_json::from_str::<Vec<KeymapSection>>(&content) {
+        Ok(sections) => sections,
+        Err(err) => {
+            log::warn!("Failed to parse {}: {err}", path.display());
+            return Vec::new();
+        }
+    };
+
+    let mut bindings = Vec::new();
+    for section in sections {
+        let predicate = match section
+            .context
+            .as_deref()
+            .map(KeyBindingContextPredicate::parse)
+            .transpose()
+        {
+            Ok(predicate) => predicate.map(Rc::new),
+            Err(err) => {
+                log::warn!("Invalid context in {}: {err}", path.display());
+                continue;
+            }
+        };
+
+        for (keystrokes, action) in section.bindings {
+            let action = match action {
+                Some(name) => match cx.build_action(&name, None) {
+                    Ok(action) => action,
+                    Err(err) => {
+                        log::warn!("Unknown action {name:?} in {}: {err}", path.display());
+                        continue;
+                    }
+                },
+                None => NoAction.boxed_clone(),
+            };
+            match KeyBinding::load(&keystrokes, action, predicate.clone(), None) {
+                Ok(binding) => bindings.push(binding),
+                Err(err) => log::warn!(
+                    "Invalid keystroke {keystrokes:?} in {}: {err}",
+                    path.display()
+                ),
+            }
+        }
+    }
+    bindings
+}
+
+/// The keystrokes currently bound to `action` in the focused view, eg. `ctrl-shift-n`.
+pub fn shortcut(action: &dyn Action, window: &Window) -> Option<String> {
+    // The last binding is the one which takes precedence
+    let binding = window.bindings_for_action(action).pop()?;
+    Some(
+        binding
+            .keystrokes()
+            .iter()
+            .map(|keystroke| keystroke.unparse())
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+fn load(cx: &mut App) {
+    cx.clear_key_bindings();
+    cx.bind_keys(default_bindings());
+    // Bindings added later take precedence so the user's win
+    cx.bind_keys(user_bindings(cx));
+}
+
+/// Bind the keymap and reload it whenever the user's keymap file changes.
+pub fn init(cx: &mut App) {
+    load(cx);
+
+    let modified = || {
+        keymap_path()
+            .and_then(|path| fs::metadata(path).ok())
+            .and_then(|metadata| metadata.modified().ok())
+    };
+    cx.spawn(async move |cx| {
+        // TODO: Use a file watcher instead of polling
+        let mut last_modified = modified();
+        loop {
+            cx.background_executor().timer(POLL_INTERVAL).await;
+
+            let modified = modified();
+            if modified != last_modified {
+                last_modified = modified;
+                if cx.update(load).is_err() {
+                    break;
+                }
+            }
+        }
+    })
+    .detach();
+}
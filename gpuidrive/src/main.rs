@@ -1,37 +1,41 @@
 use gpui::*;
 
+mod actions;
+mod archive;
 mod assets;
+mod clipboard;
 mod components;
+mod compression;
+mod config;
+mod filter;
+mod frecency;
+mod fuzzy;
+mod grep;
+mod hex;
+mod jobs;
+mod keymap;
+mod metadata;
+mod ops;
+mod permissions;
+mod preview;
+mod query;
+mod rename;
+mod search;
 mod state;
+mod transfer;
 mod window;
-
-actions!(example, [QuitApp]);
+mod xattrs;
 
 fn main() {
+    // Problems which aren't worth interrupting the user for, eg. failing to save the config, are logged as warnings
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
     Application::new()
         .with_assets(assets::Assets)
         .run(|cx: &mut App| {
-            cx.bind_keys([
-                // Window actions
-                KeyBinding::new("cmd-q", QuitApp, None),
-                KeyBinding::new("cmd-w", window::CloseWindow, None),
-                // Input
-                KeyBinding::new("backspace", components::Backspace, None),
-                KeyBinding::new("delete", components::Delete, None),
-                KeyBinding::new("left", components::Left, None),
-                KeyBinding::new("right", components::Right, None),
-                KeyBinding::new("shift-left", components::SelectLeft, None),
-                KeyBinding::new("shift-right", components::SelectRight, None),
-                KeyBinding::new("cmd-a", components::SelectAll, None),
-                KeyBinding::new("cmd-v", components::Paste, None),
-                KeyBinding::new("cmd-c", components::Copy, None),
-                KeyBinding::new("cmd-x", components::Cut, None),
-                KeyBinding::new("home", components::Home, None),
-                KeyBinding::new("end", components::End, None),
-                KeyBinding::new("ctrl-cmd-space", components::ShowCharacterPalette, None),
-            ]);
+            keymap::init(cx);
 
-            cx.on_action(|_: &QuitApp, cx| cx.quit());
+            cx.on_action(|_: &actions::QuitApp, cx| cx.quit());
             cx.on_window_closed(|cx| {
                 if cx.windows().is_empty() {
                     cx.quit();
@@ -0,0 +1,209 @@
+//! Unix metadata for the properties panel.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+
+/// Everything `stat` knows about a single node.
+#[derive(Debug, Clone)]
+pub struct Properties {
+    pub path: PathBuf,
+    pub kind: &'static str,
+    /// The target if this is a symbolic link.
+    pub link_target: Option<PathBuf>,
+    pub size: u64,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub inode: u64,
+    pub device: u64,
+    pub links: u64,
+    pub block_size: u64,
+    /// Allocated blocks, in 512 byte units.
+    pub blocks: u64,
+    pub accessed: Option<DateTime<Local>>,
+    pub modified: Option<DateTime<Local>>,
+    pub changed: Option<DateTime<Local>>,
+    /// Not every filesystem records the creation (birth) time.
+    pub created: Option<DateTime<Local>>,
+}
+
+impl Properties {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let metadata = fs::symlink_metadata(path)?;
+        let file_type = metadata.file_type();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            kind: kind_label(metadata.mode()),
+            link_target: file_type
+                .is_symlink()
+                .then(|| fs::read_link(path).ok())
+                .flatten(),
+            size: metadata.size(),
+            mode: metadata.mode(),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            inode: metadata.ino(),
+            device: metadata.dev(),
+            links: metadata.nlink(),
+            block_size: metadata.blksize(),
+            blocks: metadata.blocks(),
+            accessed: metadata.accessed().ok().map(Into::into),
+            modified: metadata.modified().ok().map(Into::into),
+            changed: DateTime::from_timestamp(metadata.ctime(), metadata.ctime_nsec() as u32)
+                .map(Into::into),
+            created: metadata.created().ok().map(Into::into),
+        })
+    }
+
+    /// The device as `major:minor`.
+    pub fn device(&self) -> String {
+        format!(
+            "{}:{}",
+            libc::major(self.device as libc::dev_t),
+            libc::minor(self.device as libc::dev_t)
+        )
+    }
+
+    pub fn allocated(&self) -> u64 {
+        self.blocks * 512
+    }
+}
+
+/// Totals for a selection of multiple nodes.
+///
+/// This doesn't recurse into directories as that could take a very long time.
+#[derive(Debug, Clone, Default)]
+pub struct Totals {
+    pub files: usize,
+    pub directories: usize,
+    pub other: usize,
+    pub size: u64,
+    pub allocated: u64,
+    pub errors: usize,
+}
+
+impl Totals {
+    pub fn load<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Self {
+        let mut totals = Self::default();
+        for path in paths {
+            let Ok(metadata) = fs::symlink_metadata(path) else {
+                totals.errors += 1;
+                continue;
+            };
+
+            if metadata.is_dir() {
+                totals.directories += 1;
+            } else if metadata.is_file() {
+                totals.files += 1;
+            } else {
+                totals.other += 1;
+            }
+            totals.size += metadata.size();
+            totals.allocated += metadata.blocks() * 512;
+        }
+        totals
+    }
+
+    pub fn count(&self) -> usize {
+        self.files + self.directories + self.other
+    }
+}
+
+fn kind_label(mode: u32) -> &'static str {
+    match mode & libc::S_IFMT as u32 {
+        m if m == libc::S_IFDIR as u32 => "Directory",
+        m if m == libc::S_IFREG as u32 => "File",
+        m if m == libc::S_IFLNK as u32 => "Symbolic link",
+        m if m == libc::S_IFIFO as u32 => "FIFO",
+        m if m == libc::S_IFSOCK as u32 => "Socket",
+        m if m == libc::S_IFCHR as u32 => "Character device",
+        m if m == libc::S_IFBLK as u32 => "Block device",
+        _ => "Unknown",
+    }
+}
+
+/// Render mode bits like `ls -l`, eg. `drwxr-xr-x`.
+pub fn mode_string(mode: u32) -> String {
+    let kind = match mode & libc::S_IFMT as u32 {
+        m if m == libc::S_IFDIR as u32 => 'd',
+        m if m == libc::S_IFLNK as u32 => 'l',
+        m if m == libc::S_IFIFO as u32 => 'p',
+        m if m == libc::S_IFSOCK as u32 => 's',
+        m if m == libc::S_IFCHR as u32 => 'c',
+        m if m == libc::S_IFBLK as u32 => 'b',
+        _ => '-',
+    };
+
+    let mut out = String::with_capacity(10);
+    out.push(kind);
+    // (read, write, execute, special bit, character when special and executable)
+    for (shift, special, special_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 0o7;
+        out.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        out.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        out.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    out
+}
+
+/// The user and group names from `/etc/passwd` and `/etc/group`.
+///
+/// This doesn't go through NSS so users from LDAP, etc won't be resolved.
+#[derive(Debug, Clone, Default)]
+pub struct Users {
+    pub users: BTreeMap<u32, String>,
+    pub groups: BTreeMap<u32, String>,
+}
+
+impl Users {
+    pub fn load() -> Self {
+        Self {
+            users: parse_id_file(Path::new("/etc/passwd")),
+            groups: parse_id_file(Path::new("/etc/group")),
+        }
+    }
+
+    pub fn user_name(&self, uid: u32) -> String {
+        self.users
+            .get(&uid)
+            .cloned()
+            .unwrap_or_else(|| uid.to_string())
+    }
+
+    pub fn group_name(&self, gid: u32) -> String {
+        self.groups
+            .get(&gid)
+            .cloned()
+            .unwrap_or_else(|| gid.to_string())
+    }
+}
+
+/// Both files are `name:password:id:...` with one entry per line.
+fn parse_id_file(path: &Path) -> BTreeMap<u32, String> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return BTreeMap::new();
+    };
+
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
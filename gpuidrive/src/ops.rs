@@ -0,0 +1,124 @@
+//! Filesystem operations which are shared between features.
+
+use std::{
+    ffi::CString,
+    fs, io,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+
+use crate::archive;
+
+/// Rename `from` to `to`, failing if `to` already exists.
+///
+/// `fs::rename` will happily overwrite the destination on Unix so we check first.
+// TODO: This is racy, use `renameat2(RENAME_NOREPLACE)`.
+pub fn rename_no_replace(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(to).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        ));
+    }
+
+    fs::rename(from, to)
+}
+
+/// Check if the current user can write to `path`.
+///
+/// Unlike `Permissions::readonly` this takes the owner, ACLs and read-only mounts into account.
+pub fn is_writable(path: &Path) -> bool {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
+
+/// Find a path for `name` in `dir` which doesn't exist yet.
+///
+/// If `name` is taken this will try `name (2).ext`, `name (3).ext`, etc.
+pub fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if fs::symlink_metadata(&path).is_err() {
+        return path;
+    }
+
+    // Archives keep their whole extension, eg. `foo (2).tar.gz` rather than `foo.tar (2).gz`
+    let stem_len = archive::stem(Path::new(name))
+        .map(|stem| stem.len())
+        .or_else(|| name.rfind('.'))
+        // A leading dot is a hidden file, not an extension
+        .filter(|&i| i > 0)
+        .unwrap_or(name.len());
+    let (stem, ext) = name.split_at(stem_len);
+
+    (2..)
+        .map(|i| dir.join(format!("{stem} ({i}){ext}")))
+        .find(|path| fs::symlink_metadata(path).is_err())
+        .expect("unbounded range")
+}
+
+/// The kinds of item which can be created with "New".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NewItem {
+    Folder,
+    File,
+    Template(PathBuf),
+}
+
+impl NewItem {
+    fn default_name(&self) -> String {
+        match self {
+            NewItem::Folder => "Untitled Folder".into(),
+            NewItem::File => "Untitled File".into(),
+            NewItem::Template(template) => template
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| "Untitled File".into()),
+        }
+    }
+}
+
+/// Create a new item in `dir`, returning its path.
+pub fn create(dir: &Path, item: &NewItem) -> io::Result<PathBuf> {
+    let path = unique_path(dir, &item.default_name());
+    match item {
+        NewItem::Folder => fs::create_dir(&path)?,
+        NewItem::File => {
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)?;
+        }
+        NewItem::Template(template) => {
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)?;
+            fs::copy(template, &path)?;
+        }
+    }
+
+    Ok(path)
+}
+
+/// The files in the user's XDG templates directory (`~/Templates` by default).
+pub fn templates() -> Vec<PathBuf> {
+    let Some(dir) =
+        dirs::template_dir().or_else(|| dirs::home_dir().map(|home| home.join("Templates")))
+    else {
+        return Vec::new();
+    };
+
+    let mut templates = fs::read_dir(dir)
+        .map(|dir| {
+            dir.filter_map(|entry| entry.ok())
+                // TODO: Support folders of templates as submenus like Nautilus
+                .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+                .map(|entry| entry.path())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    templates.sort();
+    templates
+}
@@ -0,0 +1,124 @@
+//! Changing the mode and ownership of nodes.
+
+use std::{
+    fs::{self, Permissions},
+    io,
+    os::unix::fs::{PermissionsExt, lchown},
+    path::{Path, PathBuf},
+};
+
+/// The user, group and other read/write/execute bits plus setuid, setgid and sticky.
+pub const MODE_MASK: u32 = 0o7777;
+
+/// Which mode bits to change. The rest are left as they are on each path, so changing a
+/// selection doesn't give every item the same mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModeChange {
+    /// The bits to change.
+    pub mask: u32,
+    /// Their new values.
+    pub bits: u32,
+}
+
+impl ModeChange {
+    pub fn is_empty(self) -> bool {
+        self.mask & MODE_MASK == 0
+    }
+
+    pub fn apply(self, mode: u32) -> u32 {
+        (mode & !self.mask) | (self.bits & self.mask)
+    }
+}
+
+/// A chmod/chown to apply to some paths.
+#[derive(Debug, Clone)]
+pub struct PermissionChange {
+    /// The change for directories, and files unless recursing.
+    pub mode: ModeChange,
+    /// A different change for files when recursing, so we don't make every file executable.
+    pub file_mode: ModeChange,
+    /// The new owner. `None` leaves it unchanged.
+    pub uid: Option<u32>,
+    /// The new group. `None` leaves it unchanged.
+    pub gid: Option<u32>,
+    /// Also apply the change to everything inside of directories.
+    pub recursive: bool,
+}
+
+/// Apply `change` to `paths`, returning an error for each path which failed.
+///
+/// A failure doesn't stop the rest of the paths being changed.
+pub fn apply(paths: &[PathBuf], change: &PermissionChange) -> Vec<String> {
+    let mut errors = Vec::new();
+    for path in paths {
+        apply_one(path, change, &mut errors);
+    }
+    errors
+}
+
+fn apply_one(path: &Path, change: &PermissionChange, errors: &mut Vec<String>) {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            errors.push(format!("{}: {err}", path.display()));
+            return;
+        }
+    };
+
+    if change.uid.is_some() || change.gid.is_some() {
+        lchown(path, change.uid, change.gid)
+            .unwrap_or_else(|err| errors.push(format!("{}: chown: {err}", path.display())));
+    }
+
+    // `chmod` follows symlinks and the mode of a symlink itself is meaningless on Linux
+    let mode = if change.recursive && !metadata.is_dir() {
+        change.file_mode
+    } else {
+        change.mode
+    };
+    if !metadata.is_symlink() && !mode.is_empty() {
+        let mode = mode.apply(metadata.permissions().mode()) & MODE_MASK;
+        if let Err(err) = fs::set_permissions(path, Permissions::from_mode(mode)) {
+            errors.push(format!("{}: chmod: {err}", path.display()));
+        }
+    }
+
+    if change.recursive && metadata.is_dir() {
+        match read_dir(path) {
+            Ok(children) => {
+                for child in children {
+                    apply_one(&child, change, errors);
+                }
+            }
+            Err(err) => errors.push(format!("{}: {err}", path.display())),
+        }
+    }
+}
+
+fn read_dir(path: &Path) -> io::Result<Vec<PathBuf>> {
+    fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mode_change_only_touches_edited_bits() {
+        let change = ModeChange {
+            mask: 0o020,
+            bits: 0o020,
+        };
+        assert_eq!(change.apply(0o755), 0o775);
+        assert_eq!(change.apply(0o600), 0o620);
+
+        let change = ModeChange {
+            mask: 0o111,
+            bits: 0,
+        };
+        assert_eq!(change.apply(0o755), 0o644);
+        assert!(ModeChange::default().is_empty());
+    }
+}
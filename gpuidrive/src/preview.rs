@@ -0,0 +1,305 @@
+//! Loading text files for the quick preview: detecting the encoding and highlighting the syntax.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    ops::Range,
+    path::Path,
+    sync::LazyLock,
+};
+
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Color, FontStyle, Theme, ThemeSet},
+    parsing::{SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+};
+
+use crate::{
+    archive::{self, Format},
+    grep::is_binary,
+    hex::{Magic, detect_magic},
+};
+
+/// Only this much of large files is previewed, from the start or around the line to show.
+const MAX_PREVIEW_LEN: u64 = 256 * 1024;
+/// How many lines before the line to show are read when only part of a file is previewed.
+const CONTEXT_LINES: usize = 100;
+/// Highlighting is slow for huge single line files, eg. minified code, so long lines are cut.
+const MAX_LINE_LEN: usize = 1000;
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+static THEME: LazyLock<Theme> = LazyLock::new(|| {
+    ThemeSet::load_defaults()
+        .themes
+        .remove("InspiredGitHub")
+        .expect("default themes include InspiredGitHub")
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// Anything which isn't valid UTF-8 is shown as Latin-1 rather than failing.
+    Latin1,
+}
+
+impl Encoding {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf16Le => "UTF-16 LE",
+            Self::Utf16Be => "UTF-16 BE",
+            Self::Latin1 => "Latin-1",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Span {
+    /// The byte range within the line.
+    pub range: Range<usize>,
+    /// `0xRRGGBBAA`
+    pub color: u32,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub text: String,
+    pub spans: Vec<Span>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TextPreview {
+    pub lines: Vec<Line>,
+    pub encoding: Encoding,
+    /// The name of the detected language, eg. "Rust".
+    pub language: String,
+    /// `0xRRGGBBAA`
+    pub background: u32,
+    /// Whether the file was larger than what was read.
+    pub truncated: bool,
+    /// The zero-based line number of the first line, which is only 0 when the start of the file was read.
+    pub first_line: usize,
+}
+
+impl TextPreview {
+    /// Whether the zero-based `line` of the file was read.
+    pub fn contains(&self, line: usize) -> bool {
+        (self.first_line..self.first_line + self.lines.len()).contains(&line)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Preview {
+    Text(TextPreview),
+    /// The entries of a zip or tar archive.
+    Archive {
+        format: Format,
+        /// The size of the archive itself.
+        len: u64,
+        entries: Vec<archive::Entry>,
+    },
+    /// Not something which can be shown as text, so it's shown as a hex dump.
+    Binary {
+        len: u64,
+        /// The format, if the start of the file is recognised.
+        magic: Option<Magic>,
+    },
+}
+
+/// Read the start of `path` and highlight it. This is slow so should be run in the background.
+///
+/// If the file is too large to read all of it, the part around the zero-based `line` is read instead.
+pub fn load(path: &Path, line: Option<usize>) -> io::Result<Preview> {
+    if archive::split(path).is_some_and(|(archive, _)| archive != path) {
+        // TODO: Read the entry out of the archive
+        return Err(io::Error::other(
+            "Files inside archives can't be previewed yet",
+        ));
+    }
+
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    if let Some(format) = Format::detect(path) {
+        return Ok(Preview::Archive {
+            format,
+            len,
+            entries: archive::list(path)?,
+        });
+    }
+    let first_line = match line {
+        Some(line) if len > MAX_PREVIEW_LEN => {
+            seek_to_line(&mut file, line.saturating_sub(CONTEXT_LINES))?
+        }
+        _ => 0,
+    };
+    let mut content = Vec::new();
+    file.take(MAX_PREVIEW_LEN).read_to_end(&mut content)?;
+
+    let Some((text, encoding)) = decode(&content) else {
+        return Ok(Preview::Binary {
+            len,
+            magic: detect_magic(&content),
+        });
+    };
+
+    let syntax = find_syntax(path, &text);
+    let mut highlighter = HighlightLines::new(syntax, &THEME);
+    let mut lines = Vec::new();
+    for line in LinesWithEndings::from(&text) {
+        let line = cut(line);
+        let spans = match highlighter.highlight_line(line, &SYNTAXES) {
+            Ok(regions) => {
+                let mut start = 0;
+                regions
+                    .into_iter()
+                    .map(|(style, text)| {
+                        let range = start..start + text.len();
+                        start = range.end;
+                        Span {
+                            range,
+                            color: rgba(style.foreground),
+                            bold: style.font_style.contains(FontStyle::BOLD),
+                            italic: style.font_style.contains(FontStyle::ITALIC),
+                        }
+                    })
+                    .collect()
+            }
+            // The line is still shown, just without colors
+            Err(err) => {
+                log::warn!("Failed to highlight {}: {err}", path.display());
+                Vec::new()
+            }
+        };
+
+        let text = line.trim_end_matches(['\n', '\r']).to_string();
+        let spans = spans
+            .into_iter()
+            .filter(|span: &Span| span.range.start < text.len())
+            .map(|mut span| {
+                span.range.end = span.range.end.min(text.len());
+                span
+            })
+            .collect();
+        lines.push(Line { text, spans });
+    }
+
+    Ok(Preview::Text(TextPreview {
+        lines,
+        encoding,
+        language: syntax.name.clone(),
+        background: THEME.settings.background.map_or(0xffffffff, rgba),
+        truncated: len > MAX_PREVIEW_LEN,
+        first_line,
+    }))
+}
+
+/// Move `file` to the start of the zero-based `line`, or to the start of the last line if it
+/// has fewer. Returns the line it's at.
+fn seek_to_line(file: &mut File, line: usize) -> io::Result<usize> {
+    let mut reader = BufReader::new(&mut *file);
+    let (mut offset, mut last_start) = (0, 0);
+    let mut current = 0;
+    while current < line {
+        let read = reader.skip_until(b'\n')?;
+        if read == 0 {
+            break;
+        }
+        last_start = offset;
+        offset += read as u64;
+        current += 1;
+    }
+    // The file ended without reaching the line, so show the end of it
+    if current < line {
+        current = current.saturating_sub(1);
+        offset = last_start;
+    }
+    drop(reader);
+    file.seek(SeekFrom::Start(offset))?;
+    Ok(current)
+}
+
+fn rgba(color: Color) -> u32 {
+    u32::from_be_bytes([color.r, color.g, color.b, color.a])
+}
+
+/// Cut a line down to at most `MAX_LINE_LEN` bytes.
+fn cut(line: &str) -> &str {
+    if line.len() <= MAX_LINE_LEN {
+        return line;
+    }
+    let mut end = MAX_LINE_LEN;
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+/// By extension, then by the first line, eg. a `#!/bin/sh` shebang.
+fn find_syntax(path: &Path, text: &str) -> &'static SyntaxReference {
+    path.extension()
+        .and_then(|ext| SYNTAXES.find_syntax_by_extension(&ext.to_string_lossy()))
+        .or_else(|| {
+            path.file_name()
+                .and_then(|name| SYNTAXES.find_syntax_by_extension(&name.to_string_lossy()))
+        })
+        .or_else(|| {
+            text.lines()
+                .next()
+                .and_then(|line| SYNTAXES.find_syntax_by_first_line(line))
+        })
+        .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text())
+}
+
+/// Decode text by its byte order mark, otherwise as UTF-8 falling back to Latin-1. Returns `None`
+/// for binary content.
+fn decode(content: &[u8]) -> Option<(String, Encoding)> {
+    if let Some(content) = content.strip_prefix(b"\xEF\xBB\xBF") {
+        return Some((
+            String::from_utf8_lossy(content).into_owned(),
+            Encoding::Utf8,
+        ));
+    }
+    if let Some(content) = content.strip_prefix(b"\xFF\xFE") {
+        let units = content
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]));
+        return Some((
+            char::decode_utf16(units)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect(),
+            Encoding::Utf16Le,
+        ));
+    }
+    if let Some(content) = content.strip_prefix(b"\xFE\xFF") {
+        let units = content
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]));
+        return Some((
+            char::decode_utf16(units)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect(),
+            Encoding::Utf16Be,
+        ));
+    }
+
+    if is_binary(content) {
+        return None;
+    }
+    match std::str::from_utf8(content) {
+        Ok(text) => Some((text.to_string(), Encoding::Utf8)),
+        // The read may have cut a character in half at the end
+        Err(err) if err.error_len().is_none() => Some((
+            String::from_utf8_lossy(&content[..err.valid_up_to()]).into_owned(),
+            Encoding::Utf8,
+        )),
+        Err(_) => Some((
+            content.iter().map(|&b| b as char).collect(),
+            Encoding::Latin1,
+        )),
+    }
+}
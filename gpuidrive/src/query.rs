@@ -0,0 +1,539 @@
+//! The search query language, eg. `ext:rs size:>10MB modified:<7d kind:dir name:/^test_.*/`.
+//!
+//! Words without a `key:` match names using the filter mode. Every term has to match and
+//! a `-` in front of a `key:value` term negates it.
+
+use std::{cmp::Ordering, fmt, ops::Range};
+
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use regex::{Regex, RegexBuilder};
+
+use crate::{
+    filter::{Filter, FilterMode},
+    state::{Node, NodeKind},
+};
+
+const KEYS: [&str; 6] = ["name", "ext", "size", "modified", "created", "kind"];
+
+#[derive(Debug, Clone)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+#[derive(Debug, Clone)]
+enum Term {
+    Name(Filter),
+    NameRegex(Regex),
+    /// Lowercase and without the leading `.`.
+    Ext(Vec<String>),
+    Size(Cmp, u64),
+    Modified(Cmp, Time),
+    Created(Cmp, Time),
+    Kind(NodeKind),
+    Not(Box<Term>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cmp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Time {
+    /// From a relative value like `7d`.
+    Instant(DateTime<Local>),
+    /// From a date like `2025-01-31`, compared by day.
+    Day(NaiveDate),
+}
+
+/// Where in the query text parsing failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The byte range of the offending token.
+    pub range: Range<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl ParseError {
+    fn new(range: Range<usize>, message: impl Into<String>) -> Self {
+        Self {
+            range,
+            message: message.into(),
+        }
+    }
+}
+
+impl Query {
+    pub fn parse(text: &str, mode: FilterMode) -> Result<Self, ParseError> {
+        Ok(Self {
+            terms: tokenize(text)?
+                .into_iter()
+                .map(|range| parse_term(&text[range.clone()], range, mode))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Check only the terms which don't need metadata, so we can skip reading it for most nodes.
+    pub fn matches_name(&self, name: &str) -> bool {
+        self.terms.iter().all(|term| term.matches_name(name))
+    }
+
+    /// If `node` matches, the byte offsets of the characters in its name to highlight.
+    pub fn matches(&self, node: &Node) -> Option<Vec<usize>> {
+        let mut highlights = Vec::new();
+        for term in &self.terms {
+            if !term.matches(node, &mut highlights) {
+                return None;
+            }
+        }
+        highlights.sort_unstable();
+        highlights.dedup();
+        Some(highlights)
+    }
+}
+
+impl Term {
+    fn matches_name(&self, name: &str) -> bool {
+        match self {
+            Self::Name(filter) => filter.matches(name).is_some(),
+            Self::NameRegex(regex) => regex.is_match(name),
+            Self::Not(term) => match **term {
+                Self::Name(_) | Self::NameRegex(_) => !term.matches_name(name),
+                _ => true,
+            },
+            _ => true,
+        }
+    }
+
+    fn matches(&self, node: &Node, highlights: &mut Vec<usize>) -> bool {
+        // TODO: Handle non-utf8 file names
+        let name = node.name.to_string_lossy();
+        match self {
+            Self::Name(filter) => match filter.matches(&name) {
+                Some(matched) => {
+                    highlights.extend(matched);
+                    true
+                }
+                None => false,
+            },
+            Self::NameRegex(regex) => match regex.find(&name) {
+                Some(m) => {
+                    highlights.extend(name[m.range()].char_indices().map(|(i, _)| m.start() + i));
+                    true
+                }
+                None => false,
+            },
+            Self::Ext(extensions) => node
+                .path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .is_some_and(|ext| extensions.contains(&ext)),
+            Self::Size(cmp, size) => cmp.test(node.size.cmp(size)),
+            Self::Modified(cmp, time) => time.test(*cmp, node.modified),
+            Self::Created(cmp, time) => time.test(*cmp, node.created),
+            Self::Kind(kind) => node.kind == *kind,
+            // Negated names aren't highlighted
+            Self::Not(term) => !term.matches(node, &mut Vec::new()),
+        }
+    }
+}
+
+impl Cmp {
+    fn test(self, ordering: Ordering) -> bool {
+        match self {
+            Self::Lt => ordering.is_lt(),
+            Self::Le => ordering.is_le(),
+            Self::Gt => ordering.is_gt(),
+            Self::Ge => ordering.is_ge(),
+            Self::Eq => ordering.is_eq(),
+        }
+    }
+
+    /// Split the comparison off the front of a value, eg. `>=10MB`. No comparison means equal.
+    fn parse(value: &str) -> (Self, &str) {
+        for (prefix, cmp) in [
+            ("<=", Self::Le),
+            (">=", Self::Ge),
+            ("<", Self::Lt),
+            (">", Self::Gt),
+            ("=", Self::Eq),
+        ] {
+            if let Some(rest) = value.strip_prefix(prefix) {
+                return (cmp, rest);
+            }
+        }
+        (Self::Eq, value)
+    }
+}
+
+impl Time {
+    fn test(self, cmp: Cmp, time: DateTime<Local>) -> bool {
+        match self {
+            Self::Instant(instant) => cmp.test(time.cmp(&instant)),
+            Self::Day(day) => cmp.test(time.date_naive().cmp(&day)),
+        }
+    }
+}
+
+/// Split the query into the byte ranges of its tokens.
+///
+/// Tokens are separated by whitespace, except within `"quotes"` or a `/regex/` after a `:`.
+fn tokenize(text: &str) -> Result<Vec<Range<usize>>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut end = text.len();
+        let mut quote = None;
+        let mut previous = None;
+        while let Some(&(i, c)) = chars.peek() {
+            match quote {
+                None if c.is_whitespace() => {
+                    end = i;
+                    break;
+                }
+                None if c == '"' => quote = Some('"'),
+                None if c == '/' && previous == Some(':') => quote = Some('/'),
+                Some(q) if c == q && previous != Some('\\') => quote = None,
+                _ => {}
+            }
+            previous = Some(c);
+            chars.next();
+        }
+
+        if let Some(quote) = quote {
+            return Err(ParseError::new(
+                start..end,
+                format!("Missing closing {quote}"),
+            ));
+        }
+        tokens.push(start..end);
+    }
+    Ok(tokens)
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn parse_term(token: &str, range: Range<usize>, mode: FilterMode) -> Result<Term, ParseError> {
+    if let Some(rest) = token.strip_prefix('-').filter(|rest| rest.contains(':')) {
+        return Ok(Term::Not(Box::new(parse_term(
+            rest,
+            range.start + 1..range.end,
+            mode,
+        )?)));
+    }
+
+    let Some((key, value)) = token.split_once(':') else {
+        return Ok(Term::Name(Filter::new(unquote(token), mode)));
+    };
+    if !KEYS.contains(&key) {
+        // So names with a colon in them can still be searched for
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Ok(Term::Name(Filter::new(unquote(token), mode)));
+        }
+        return Err(ParseError::new(
+            range.start..range.start + key.len(),
+            format!(
+                "Unknown filter '{key}', expected one of {}",
+                KEYS.join(", ")
+            ),
+        ));
+    }
+
+    let value_range = range.start + key.len() + 1..range.end;
+    let error = |message: String| ParseError::new(value_range.clone(), message);
+    if value.is_empty() {
+        return Err(ParseError::new(
+            range,
+            format!("Missing a value for '{key}'"),
+        ));
+    }
+
+    Ok(match key {
+        "name" => match value
+            .strip_prefix('/')
+            .and_then(|value| value.strip_suffix('/'))
+        {
+            Some(pattern) => Term::NameRegex(
+                RegexBuilder::new(pattern)
+                    .case_insensitive(!pattern.chars().any(char::is_uppercase))
+                    .build()
+                    .map_err(|err| {
+                        // The last line of the error is the reason, the rest points at the pattern
+                        let err = err.to_string();
+                        let reason = err.lines().last().unwrap_or_default();
+                        error(format!(
+                            "Invalid regex: {}",
+                            reason.trim_start_matches("error: ")
+                        ))
+                    })?,
+            ),
+            None => {
+                let value = unquote(value);
+                let mode = if value.contains(['*', '?', '[']) {
+                    FilterMode::Glob
+                } else {
+                    mode
+                };
+                Term::Name(Filter::new(value, mode))
+            }
+        },
+        "ext" => Term::Ext(
+            value
+                .split(',')
+                .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                .filter(|ext| !ext.is_empty())
+                .collect(),
+        ),
+        "size" => {
+            let (cmp, size) = Cmp::parse(value);
+            Term::Size(
+                cmp,
+                parse_size(size).ok_or_else(|| {
+                    error(format!(
+                        "Invalid size '{size}', expected something like 10MB"
+                    ))
+                })?,
+            )
+        }
+        "modified" | "created" => {
+            let (cmp, time) = Cmp::parse(value);
+            let (cmp, time) = parse_time(cmp, time).ok_or_else(|| {
+                error(format!(
+                    "Invalid time '{time}', expected something like 7d or 2025-01-31"
+                ))
+            })?;
+            if key == "modified" {
+                Term::Modified(cmp, time)
+            } else {
+                Term::Created(cmp, time)
+            }
+        }
+        "kind" => Term::Kind(match value.to_lowercase().as_str() {
+            "file" => NodeKind::File,
+            "dir" | "directory" | "folder" => NodeKind::Directory,
+            "other" => NodeKind::Unknown,
+            _ => {
+                return Err(error(format!(
+                    "Invalid kind '{value}', expected file, dir or other"
+                )));
+            }
+        }),
+        _ => unreachable!("all keys are handled"),
+    })
+}
+
+/// Parse a size like `10MB`, `1.5g` or `512`. Units are powers of 1024 like `human_bytes` shows.
+fn parse_size(value: &str) -> Option<u64> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number = number.parse::<f64>().ok()?;
+    let exponent = match unit.to_lowercase().as_str() {
+        "" | "b" => 0,
+        "k" | "kb" | "kib" => 1,
+        "m" | "mb" | "mib" => 2,
+        "g" | "gb" | "gib" => 3,
+        "t" | "tb" | "tib" => 4,
+        _ => return None,
+    };
+    Some((number * 1024f64.powi(exponent)) as u64)
+}
+
+/// Parse a date or an age like `7d`, returning the comparison to do against the node's time.
+fn parse_time(cmp: Cmp, value: &str) -> Option<(Cmp, Time)> {
+    if let Ok(day) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some((cmp, Time::Day(day)));
+    }
+
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number = number.parse::<i64>().ok()?;
+    let age = match unit {
+        "s" => Duration::try_seconds(number),
+        "min" => Duration::try_minutes(number),
+        "h" => Duration::try_hours(number),
+        "d" => Duration::try_days(number),
+        "w" => Duration::try_weeks(number),
+        "mo" => Duration::try_days(number.checked_mul(30)?),
+        "y" => Duration::try_days(number.checked_mul(365)?),
+        _ => None,
+    }?;
+
+    // A smaller age is a later time so the comparison flips, and `7d` on its own means within 7 days
+    let cmp = match cmp {
+        Cmp::Lt => Cmp::Gt,
+        Cmp::Le | Cmp::Eq => Cmp::Ge,
+        Cmp::Gt => Cmp::Lt,
+        Cmp::Ge => Cmp::Le,
+    };
+    Some((cmp, Time::Instant(Local::now().checked_sub_signed(age)?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsString, path::PathBuf};
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn node(name: &str, kind: NodeKind, size: u64) -> Node {
+        let modified = Local.with_ymd_and_hms(2025, 1, 31, 12, 0, 0).unwrap();
+        Node {
+            path: PathBuf::from("/nonexistent").join(name),
+            name: OsString::from(name),
+            kind,
+            size,
+            created: modified,
+            modified,
+            hidden: false,
+        }
+    }
+
+    fn matches(query: &str, node: &Node) -> bool {
+        Query::parse(query, FilterMode::Substring)
+            .unwrap()
+            .matches(node)
+            .is_some()
+    }
+
+    fn error(query: &str) -> ParseError {
+        Query::parse(query, FilterMode::Substring).unwrap_err()
+    }
+
+    #[test]
+    fn tokens_keep_quotes_and_regexes_together() {
+        let text = r#"foo "two words" name:/a b/ -ext:rs"#;
+        let tokens = tokenize(text)
+            .unwrap()
+            .into_iter()
+            .map(|range| &text[range])
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, ["foo", "\"two words\"", "name:/a b/", "-ext:rs"]);
+
+        assert_eq!(error("\"open").message, "Missing closing \"");
+    }
+
+    #[test]
+    fn words_match_names() {
+        let report = node("Report 2025.pdf", NodeKind::File, 0);
+        assert!(matches("report", &report));
+        assert!(matches("\"report 2025\"", &report));
+        assert!(!matches("invoice", &report));
+        // Every term has to match
+        assert!(!matches("report invoice", &report));
+        assert!(Query::parse("", FilterMode::Substring).unwrap().is_empty());
+
+        let highlights = Query::parse("port", FilterMode::Substring)
+            .unwrap()
+            .matches(&report);
+        assert_eq!(highlights, Some(vec![2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn keys() {
+        let video = node("holiday.MP4", NodeKind::File, 3 * 1024 * 1024 * 1024);
+        let dir = node("src", NodeKind::Directory, 0);
+
+        assert!(matches("ext:mp4", &video));
+        assert!(matches("ext:.mov,mp4", &video));
+        assert!(!matches("ext:mov", &video));
+        assert!(matches("size:>1GB", &video));
+        assert!(matches("size:>=3g", &video));
+        assert!(!matches("size:<1.5GB", &video));
+        assert!(matches("kind:dir", &dir));
+        assert!(!matches("kind:file", &dir));
+        assert!(matches("name:hol*", &video));
+        // Regexes are case-insensitive unless they contain an uppercase letter
+        assert!(matches("name:/^hol.*mp4$/", &video));
+        assert!(!matches("name:/^HOL/", &video));
+        assert!(!matches("name:/^day/", &video));
+    }
+
+    #[test]
+    fn negation() {
+        let video = node("holiday.mp4", NodeKind::File, 0);
+        assert!(matches("-ext:mov", &video));
+        assert!(!matches("-ext:mp4", &video));
+        assert!(!matches("-kind:file", &video));
+        // Without a key it's part of the name
+        assert!(matches("-", &node("a-b", NodeKind::File, 0)));
+    }
+
+    #[test]
+    fn times() {
+        let old = node("old.txt", NodeKind::File, 0);
+        assert!(matches("modified:2025-01-31", &old));
+        assert!(matches("modified:>2025-01-01", &old));
+        assert!(!matches("modified:<2025-01-31", &old));
+        assert!(matches("created:<=2025-01-31", &old));
+        // An age on its own means within that age
+        assert!(!matches("modified:7d", &old));
+        assert!(matches("modified:>7d", &old));
+    }
+
+    #[test]
+    fn names_with_colons_are_not_keys() {
+        let node = node("12:30 notes", NodeKind::File, 0);
+        assert!(matches("12:30", &node));
+    }
+
+    #[test]
+    fn errors_point_at_the_token() {
+        let err = error("report sise:>1MB");
+        assert_eq!(err.range, 7..11);
+        assert!(err.message.starts_with("Unknown filter 'sise'"));
+
+        let err = error("size:big");
+        assert_eq!(err.range, 5..8);
+        assert_eq!(
+            err.message,
+            "Invalid size 'big', expected something like 10MB"
+        );
+
+        let err = error("ext:");
+        assert_eq!(err.range, 0..4);
+
+        assert_eq!(error("kind:pipe").range, 5..9);
+        assert_eq!(error("modified:yesterday").range, 9..18);
+        assert_eq!(error("modified:<100000000d").range, 9..20);
+        assert!(error("name:/(/").message.starts_with("Invalid regex"));
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("10KB"), Some(10 * 1024));
+        assert_eq!(parse_size("1.5m"), Some(1024 * 1024 * 3 / 2));
+        assert_eq!(parse_size("2TiB"), Some(2 * 1024u64.pow(4)));
+        assert_eq!(parse_size("10 parsecs"), None);
+        assert_eq!(parse_size("MB"), None);
+    }
+}
@@ -0,0 +1,487 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use regex::Regex;
+
+use crate::{ops::rename_no_replace, state::Node};
+
+/// The options for a batch rename.
+///
+/// The name of each node is transformed by:
+///  1. Applying the find/replace to the full file name. In regex mode `$1`/`${name}` refer to capture groups.
+///  2. Expanding `template`. `{name}` is the name without its extension, `{ext}` is the extension including the dot,
+///     `{n}` is the counter (`{n:3}` pads it to 3 digits) and `{date}` is the modified date (`{date:%Y%m%d}` for a custom format).
+///     Use `{{` and `}}` for literal braces.
+///  3. Converting the case of the result. Title case leaves the extension alone.
+#[derive(Debug, Clone)]
+pub struct RenameOptions {
+    pub find: String,
+    pub replace: String,
+    pub regex: bool,
+    pub template: String,
+    pub counter_start: usize,
+    pub case: CaseConversion,
+}
+
+impl Default for RenameOptions {
+    fn default() -> Self {
+        Self {
+            find: String::new(),
+            replace: String::new(),
+            regex: false,
+            template: "{name}{ext}".into(),
+            counter_start: 1,
+            case: CaseConversion::Keep,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseConversion {
+    Keep,
+    Lower,
+    Upper,
+    Title,
+}
+
+impl CaseConversion {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Keep => Self::Lower,
+            Self::Lower => Self::Upper,
+            Self::Upper => Self::Title,
+            Self::Title => Self::Keep,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Keep => "Keep case",
+            Self::Lower => "lowercase",
+            Self::Upper => "UPPERCASE",
+            Self::Title => "Title Case",
+        }
+    }
+
+    /// Convert the case of a file name. Title case only touches the stem, so `foo.txt` becomes `Foo.txt` rather than `Foo.Txt`.
+    fn apply(self, name: &str) -> String {
+        match self {
+            Self::Title => {
+                let (stem, ext) = split_extension(name);
+                self.apply_str(stem) + ext
+            }
+            _ => self.apply_str(name),
+        }
+    }
+
+    fn apply_str(self, s: &str) -> String {
+        match self {
+            Self::Keep => s.to_string(),
+            Self::Lower => s.to_lowercase(),
+            Self::Upper => s.to_uppercase(),
+            Self::Title => {
+                let mut out = String::with_capacity(s.len());
+                let mut start_of_word = true;
+                for c in s.chars() {
+                    if c.is_alphanumeric() {
+                        if start_of_word {
+                            out.extend(c.to_uppercase());
+                        } else {
+                            out.extend(c.to_lowercase());
+                        }
+                        start_of_word = false;
+                    } else {
+                        out.push(c);
+                        start_of_word = true;
+                    }
+                }
+                out
+            }
+        }
+    }
+}
+
+/// A single row of the rename preview.
+#[derive(Debug, Clone)]
+pub struct RenameEntry {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub old_name: String,
+    pub new_name: String,
+    pub conflict: Option<Conflict>,
+}
+
+impl RenameEntry {
+    pub fn is_unchanged(&self) -> bool {
+        self.from == self.to
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conflict {
+    /// The new name is empty or contains a path separator.
+    Invalid,
+    /// Another entry is being renamed to the same name.
+    Duplicate,
+    /// A file which isn't part of this rename already has this name.
+    Exists,
+}
+
+impl Conflict {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Invalid => "Invalid name",
+            Self::Duplicate => "Duplicate name",
+            Self::Exists => "Already exists",
+        }
+    }
+}
+
+/// Compute the new names for `nodes` without touching the filesystem (except for checking for existing files).
+pub fn preview(nodes: &[Rc<Node>], options: &RenameOptions) -> Result<Vec<RenameEntry>, String> {
+    let regex = if options.regex && !options.find.is_empty() {
+        Some(Regex::new(&options.find).map_err(|err| err.to_string())?)
+    } else {
+        None
+    };
+
+    let mut entries = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| {
+            // TODO: Handle non-utf8 file names
+            let old_name = node.name.to_string_lossy().to_string();
+
+            let replaced = match &regex {
+                Some(regex) => regex
+                    .replace_all(&old_name, options.replace.as_str())
+                    .to_string(),
+                None if !options.find.is_empty() => {
+                    old_name.replace(&options.find, &options.replace)
+                }
+                None => old_name.clone(),
+            };
+
+            let new_name = options.case.apply(&expand_template(
+                &options.template,
+                &replaced,
+                node,
+                options.counter_start + i,
+            )?);
+
+            Ok(RenameEntry {
+                from: node.path.clone(),
+                to: node.path.with_file_name(&new_name),
+                old_name,
+                new_name,
+                conflict: None,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let sources = entries
+        .iter()
+        .map(|e| e.from.clone())
+        .collect::<HashSet<_>>();
+    let mut targets = HashMap::<PathBuf, usize>::new();
+    for entry in &entries {
+        *targets.entry(entry.to.clone()).or_default() += 1;
+    }
+
+    for entry in &mut entries {
+        entry.conflict = if entry.new_name.is_empty()
+            || entry.new_name == "."
+            || entry.new_name == ".."
+            || entry.new_name.contains('/')
+        {
+            Some(Conflict::Invalid)
+        } else if targets[&entry.to] > 1 {
+            Some(Conflict::Duplicate)
+        } else if !entry.is_unchanged()
+            && !sources.contains(&entry.to)
+            && exists_elsewhere(&entry.from, &entry.to)
+        {
+            Some(Conflict::Exists)
+        } else {
+            None
+        };
+    }
+
+    Ok(entries)
+}
+
+/// Whether `to` is a different file from `from`. On case-insensitive filesystems, eg. macOS by
+/// default, `Foo.txt` is the same file as `foo.txt` so renaming to it isn't a conflict.
+fn exists_elsewhere(from: &Path, to: &Path) -> bool {
+    match (fs::symlink_metadata(from), fs::symlink_metadata(to)) {
+        (Ok(from), Ok(to)) => from.dev() != to.dev() || from.ino() != to.ino(),
+        (_, to) => to.is_ok(),
+    }
+}
+
+/// Split a file name into its stem and extension, including the dot.
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        // A leading dot is a hidden file, not an extension
+        Some(i) if i > 0 => (&name[..i], &name[i..]),
+        _ => (name, ""),
+    }
+}
+
+fn expand_template(
+    template: &str,
+    name: &str,
+    node: &Node,
+    counter: usize,
+) -> Result<String, String> {
+    let (stem, ext) = split_extension(name);
+
+    let mut out = String::with_capacity(template.len() + name.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut token = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => token.push(c),
+                        None => return Err(format!("Unclosed token '{{{token}'")),
+                    }
+                }
+
+                let (key, arg) = match token.split_once(':') {
+                    Some((key, arg)) => (key, Some(arg)),
+                    None => (token.as_str(), None),
+                };
+                match (key, arg) {
+                    ("name", None) => out.push_str(stem),
+                    ("ext", None) => out.push_str(ext),
+                    ("n", None) => out.push_str(&counter.to_string()),
+                    ("n", Some(width)) => {
+                        let width = width
+                            .parse::<usize>()
+                            .map_err(|_| format!("Invalid counter width '{width}'"))?;
+                        out.push_str(&format!("{counter:0width$}"));
+                    }
+                    ("date", format) => {
+                        use std::fmt::Write;
+
+                        write!(
+                            out,
+                            "{}",
+                            node.modified.format(format.unwrap_or("%Y-%m-%d"))
+                        )
+                        .map_err(|_| {
+                            format!("Invalid date format '{}'", format.unwrap_or_default())
+                        })?;
+                    }
+                    _ => return Err(format!("Unknown token '{{{token}}}'")),
+                }
+            }
+            c => out.push(c),
+        }
+    }
+
+    Ok(out)
+}
+
+/// Apply the renames. If any step fails every rename which has already been done is reverted.
+///
+/// This is done in two phases (to a temporary name and then to the final name) so swapping names
+/// (`a -> b`, `b -> a`) works.
+pub fn apply(entries: &[RenameEntry]) -> io::Result<()> {
+    if let Some(entry) = entries.iter().find(|e| e.conflict.is_some()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}: {}", entry.old_name, entry.conflict.unwrap().label()),
+        ));
+    }
+
+    let entries = entries
+        .iter()
+        .filter(|e| !e.is_unchanged())
+        .collect::<Vec<_>>();
+    let mut done = Vec::<(PathBuf, PathBuf)>::with_capacity(entries.len() * 2);
+
+    let result = (|| {
+        let mut temporary = Vec::with_capacity(entries.len());
+        for (i, entry) in entries.iter().enumerate() {
+            let tmp = entry
+                .from
+                .with_file_name(format!(".gpuidrive-rename-{}-{i}", std::process::id()));
+            rename_no_replace(&entry.from, &tmp)?;
+            done.push((entry.from.clone(), tmp.clone()));
+            temporary.push(tmp);
+        }
+
+        for (entry, tmp) in entries.iter().zip(temporary) {
+            rename_no_replace(&tmp, &entry.to)?;
+            done.push((tmp, entry.to.clone()));
+        }
+
+        Ok(())
+    })();
+
+    if result.is_err() {
+        for (from, to) in done.into_iter().rev() {
+            // TODO: Surface rollback failures to the user
+            let _ = fs::rename(&to, &from);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use chrono::{Local, TimeZone};
+
+    use crate::state::NodeKind;
+
+    use super::*;
+
+    fn node(name: &str) -> Node {
+        let modified = Local.with_ymd_and_hms(2025, 1, 31, 12, 0, 0).unwrap();
+        Node {
+            path: PathBuf::from("/nonexistent").join(name),
+            name: OsString::from(name),
+            kind: NodeKind::File,
+            size: 0,
+            created: modified,
+            modified,
+            hidden: false,
+        }
+    }
+
+    fn new_names(names: &[&str], options: &RenameOptions) -> Result<Vec<String>, String> {
+        let nodes = names
+            .iter()
+            .map(|name| Rc::new(node(name)))
+            .collect::<Vec<_>>();
+        Ok(preview(&nodes, options)?
+            .into_iter()
+            .map(|entry| entry.new_name)
+            .collect())
+    }
+
+    #[test]
+    fn case_conversion() {
+        assert_eq!(
+            CaseConversion::Title.apply("hello world.txt"),
+            "Hello World.txt"
+        );
+        assert_eq!(CaseConversion::Title.apply("photo.JPG"), "Photo.JPG");
+        assert_eq!(CaseConversion::Upper.apply("notes.md"), "NOTES.MD");
+        assert_eq!(CaseConversion::Lower.apply("README.MD"), "readme.md");
+        assert_eq!(CaseConversion::Title.apply(".bashrc"), ".Bashrc");
+        assert_eq!(CaseConversion::Keep.apply("MiXeD.Txt"), "MiXeD.Txt");
+    }
+
+    #[test]
+    fn template_tokens() {
+        let node = node("photo.jpg");
+        let expand = |template| expand_template(template, "photo.jpg", &node, 7);
+        assert_eq!(expand("{name}{ext}").unwrap(), "photo.jpg");
+        assert_eq!(expand("{n:3}-{name}{ext}").unwrap(), "007-photo.jpg");
+        assert_eq!(expand("{date}_{n}{ext}").unwrap(), "2025-01-31_7.jpg");
+        assert_eq!(expand("{date:%Y%m%d}{ext}").unwrap(), "20250131.jpg");
+        assert_eq!(expand("{{name}}").unwrap(), "{name}");
+    }
+
+    #[test]
+    fn template_errors() {
+        let node = node("a.txt");
+        assert!(expand_template("{name", "a.txt", &node, 1).is_err());
+        assert!(expand_template("{nope}", "a.txt", &node, 1).is_err());
+        assert!(expand_template("{n:x}", "a.txt", &node, 1).is_err());
+    }
+
+    #[test]
+    fn find_and_replace() {
+        let options = RenameOptions {
+            find: "IMG_".into(),
+            replace: "holiday-".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            new_names(&["IMG_1.jpg", "IMG_2.jpg", "other.jpg"], &options).unwrap(),
+            ["holiday-1.jpg", "holiday-2.jpg", "other.jpg"]
+        );
+    }
+
+    #[test]
+    fn regex_captures() {
+        let options = RenameOptions {
+            find: r"^(\w+)-(\d+)".into(),
+            replace: "${2}_$1".into(),
+            regex: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            new_names(&["report-2024.pdf"], &options).unwrap(),
+            ["2024_report.pdf"]
+        );
+
+        let invalid = RenameOptions {
+            find: "(".into(),
+            regex: true,
+            ..Default::default()
+        };
+        assert!(new_names(&["a"], &invalid).is_err());
+    }
+
+    #[test]
+    fn conflicts() {
+        let nodes = ["a.txt", "b.txt"].map(|name| Rc::new(node(name)));
+        let options = RenameOptions {
+            template: "same{ext}".into(),
+            ..Default::default()
+        };
+        let entries = preview(&nodes, &options).unwrap();
+        assert!(
+            entries
+                .iter()
+                .all(|e| e.conflict == Some(Conflict::Duplicate))
+        );
+
+        let options = RenameOptions {
+            find: "a.txt".into(),
+            replace: "".into(),
+            ..Default::default()
+        };
+        let entries = preview(&nodes, &options).unwrap();
+        assert_eq!(entries[0].conflict, Some(Conflict::Invalid));
+        assert_eq!(entries[1].conflict, None);
+    }
+
+    #[test]
+    fn case_only_rename_is_not_a_conflict() {
+        let dir = std::env::temp_dir().join(format!("gpuidrive-rename-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (from, other) = (dir.join("foo.txt"), dir.join("bar.txt"));
+        fs::write(&from, "").unwrap();
+        fs::write(&other, "").unwrap();
+
+        assert!(!exists_elsewhere(&from, &from));
+        assert!(exists_elsewhere(&from, &other));
+        assert!(!exists_elsewhere(&from, &dir.join("missing.txt")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
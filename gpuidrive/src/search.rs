@@ -0,0 +1,116 @@
+//! Finding nodes by name anywhere below a directory.
+
+use std::{
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use futures::{StreamExt, channel::mpsc};
+use gpui::{Context, Task};
+
+use crate::{
+    query::Query,
+    state::{HiddenNames, Node, State},
+};
+
+/// How often the listing is updated with new results while the walk is running.
+const BATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A recursive search which streams its results into the [`State`] that started it.
+///
+/// Dropping it cancels the walk.
+pub struct Search {
+    pub root: PathBuf,
+    pub query: Query,
+    /// Set once the walk has visited everything or was cancelled.
+    pub finished: bool,
+    cancelled: Arc<AtomicBool>,
+    _task: Task<()>,
+}
+
+impl Search {
+    /// Walk `root` on the background executor, adding every node matching `query` to the listing.
+    pub fn start(cx: &mut Context<State>, root: PathBuf, query: Query, show_hidden: bool) -> Self {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (tx, mut rx) = mpsc::unbounded();
+
+        cx.background_executor()
+            .spawn({
+                let (root, query, cancelled) = (root.clone(), query.clone(), cancelled.clone());
+                async move {
+                    let hidden_names = Mutex::new(HiddenNames::default());
+                    let is_hidden = |entry: &indexer::DirEntry| {
+                        hidden_names
+                            .lock()
+                            .unwrap_or_else(|err| err.into_inner())
+                            .is_hidden(entry.path())
+                    };
+
+                    indexer::walk(
+                        &root,
+                        &cancelled,
+                        // Hidden directories aren't walked at all
+                        |entry| show_hidden || !is_hidden(entry),
+                        |entry| {
+                            // TODO: Handle non-utf8 file names
+                            if !query.matches_name(&entry.file_name().to_string_lossy()) {
+                                return;
+                            }
+
+                            // Anything hidden has already been skipped unless hidden files are shown
+                            let hidden = show_hidden && is_hidden(entry);
+                            match Node::with_hidden(entry.path().to_path_buf(), hidden) {
+                                Ok(node) if query.matches(&node).is_some() => {
+                                    tx.unbounded_send(node).ok();
+                                }
+                                _ => {}
+                            }
+                        },
+                    );
+                }
+            })
+            .detach();
+
+        let _task = cx.spawn(async move |this, cx| {
+            while let Some(node) = rx.next().await {
+                let mut nodes = vec![node];
+                while let Ok(Some(node)) = rx.try_next() {
+                    nodes.push(node);
+                }
+
+                if this
+                    .update(cx, |state, cx| state.add_search_results(cx, nodes))
+                    .is_err()
+                {
+                    return;
+                }
+                cx.background_executor().timer(BATCH_INTERVAL).await;
+            }
+
+            this.update(cx, |state, cx| state.finish_search(cx)).ok();
+        });
+
+        Self {
+            root,
+            query,
+            finished: false,
+            cancelled,
+            _task,
+        }
+    }
+
+    /// Stop walking, keeping the results found so far.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Search {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
@@ -1,16 +1,52 @@
-use std::{ffi::OsString, fs::FileType, os::unix::fs::MetadataExt, path::PathBuf, rc::Rc};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    ffi::{OsStr, OsString},
+    fs::{self, FileType},
+    io,
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use chrono::{DateTime, Local};
-use gpui::{Context, EventEmitter};
+use gpui::{Context, EventEmitter, Task};
+
+use crate::{
+    archive,
+    config::{Config, SmartFolder},
+    frecency,
+    ops::{self, NewItem},
+    query::Query,
+    search::Search,
+};
 
 pub struct State {
+    /// Every node in the current directory, or the results of `search`.
+    entries: Vec<Rc<Node>>,
+    /// The nodes matching `filter`. Selection indexes are into this.
     nodes: Vec<Rc<Node>>,
+    /// The byte offsets of the characters in each node's name which matched the filter.
+    highlights: Vec<Vec<usize>>,
+    filter: Option<Query>,
+    /// While searching the listing shows matches from every directory below the current one.
+    search: Option<Search>,
+    /// The smart folder being shown. It's a virtual directory listing the results of its search.
+    smart_folder: Option<SmartFolder>,
 
     backward: Vec<PathBuf>,
     forward: Vec<PathBuf>,
     current: PathBuf,
 
     selected: Option<usize>,
+    /// Every selected row. `selected` is the cursor within it.
+    selection: BTreeSet<usize>,
+
+    show_hidden: bool,
+    /// The archive the current directory is inside of, and its entries so moving around within
+    /// it doesn't read it again.
+    archive: Option<(PathBuf, Rc<Vec<archive::Entry>>)>,
+    /// The archive being read in the background, once the current directory moves into one.
+    loading_archive: Option<(PathBuf, Task<()>)>,
 }
 
 /// Represents a node on the filesystem.
@@ -22,6 +58,8 @@ pub struct Node {
     pub size: u64,
     pub created: DateTime<Local>,
     pub modified: DateTime<Local>,
+    /// Dotfiles and anything listed in the directory's `.hidden` file.
+    pub hidden: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -32,6 +70,67 @@ pub enum NodeKind {
              // TODO: Handle symbolic links, etc
 }
 
+impl Node {
+    pub fn from_path(path: PathBuf) -> io::Result<Self> {
+        let hidden = HiddenNames::default().is_hidden(&path);
+        Self::with_hidden(path, hidden)
+    }
+
+    /// Like [`Node::from_path`] for when it's already known whether the node is hidden.
+    pub fn with_hidden(path: PathBuf, hidden: bool) -> io::Result<Self> {
+        let metadata = fs::symlink_metadata(&path)?;
+        let name = path.file_name().unwrap_or(path.as_os_str()).to_owned();
+
+        Ok(Self {
+            name,
+            hidden,
+            kind: metadata.file_type().into(),
+            size: metadata.size(),
+            created: metadata.created()?.into(),
+            modified: metadata.modified()?.into(),
+            path,
+        })
+    }
+}
+
+/// The names listed in a directory's `.hidden` file, one per line (the GNOME convention).
+fn hidden_names(dir: &Path) -> HashSet<OsString> {
+    fs::read(dir.join(".hidden"))
+        .map(|content| {
+            content
+                .split(|b| *b == b'\n')
+                .filter(|line| !line.is_empty())
+                .map(|line| std::ffi::OsStr::from_bytes(line).to_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn is_hidden(name: &OsStr, hidden_names: &HashSet<OsString>) -> bool {
+    name.as_bytes().starts_with(b".") || hidden_names.contains(name)
+}
+
+/// Reads each directory's `.hidden` file only once, for checking many paths across directories.
+#[derive(Default)]
+pub struct HiddenNames(HashMap<PathBuf, HashSet<OsString>>);
+
+impl HiddenNames {
+    /// Whether `path` is a dotfile or listed in its directory's `.hidden` file.
+    pub fn is_hidden(&mut self, path: &Path) -> bool {
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        match path.parent() {
+            Some(dir) => {
+                let names = self
+                    .0
+                    .entry(dir.to_path_buf())
+                    .or_insert_with(|| hidden_names(dir));
+                is_hidden(name, names)
+            }
+            None => is_hidden(name, &HashSet::new()),
+        }
+    }
+}
+
 impl From<FileType> for NodeKind {
     fn from(value: FileType) -> Self {
         if value.is_dir() {
@@ -45,19 +144,28 @@ impl From<FileType> for NodeKind {
 }
 
 impl State {
-    pub fn init() -> Self {
+    pub fn init(cx: &mut Context<Self>) -> Self {
         let current = PathBuf::from("/Users/oscar/Desktop"); // TODO: Don't hardcode username
         let current = PathBuf::from("/Users/oscar/Library/pnpm/store/v10/files"); // TODO
         // let current = PathBuf::from("/Users/oscar/Desktop/sdtest"); // TODO
 
         let mut this = Self {
+            entries: Default::default(),
             nodes: Default::default(),
+            highlights: Default::default(),
+            filter: None,
+            search: None,
+            smart_folder: None,
             backward: Default::default(),
             forward: Default::default(),
             current,
             selected: None,
+            selection: Default::default(),
+            show_hidden: Config::load().show_hidden,
+            archive: None,
+            loading_archive: None,
         };
-        this.load_content();
+        this.load_content(cx);
         this
     }
 
@@ -69,88 +177,521 @@ impl State {
         &self.nodes
     }
 
+    pub fn highlights(&self, ix: usize) -> &[usize] {
+        self.highlights
+            .get(ix)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Only show the nodes matching `filter`, keeping the selection if it's still visible.
+    pub fn set_filter(&mut self, cx: &mut Context<Self>, filter: Option<Query>) {
+        let selected = self
+            .selected_nodes()
+            .iter()
+            .map(|node| node.path.clone())
+            .collect::<Vec<_>>();
+
+        self.filter = filter.filter(|filter| !filter.is_empty());
+        self.apply_filter();
+        self.select_paths(cx, &selected);
+
+        // Select the first match so enter opens it
+        if self.selected.is_none() && self.filter.is_some() && !self.nodes.is_empty() {
+            self.set_selection(cx, 0);
+        }
+    }
+
+    fn apply_filter(&mut self) {
+        // Search results already match the search, but it's used to highlight them unless they are filtered further
+        let filter = self
+            .filter
+            .as_ref()
+            .or(self.search.as_ref().map(|search| &search.query));
+        let (nodes, highlights) = self
+            .entries
+            .iter()
+            .filter_map(|node| {
+                let Some(filter) = filter else {
+                    return Some((node.clone(), Vec::new()));
+                };
+                filter
+                    .matches(node)
+                    .map(|highlights| (node.clone(), highlights))
+            })
+            .unzip();
+        self.nodes = nodes;
+        self.highlights = highlights;
+    }
+
+    pub fn searching(&self) -> Option<&Search> {
+        self.search.as_ref()
+    }
+
+    /// Replace the listing with the nodes below the current directory matching `query`.
+    ///
+    /// `None` ends the search and goes back to the directory's own listing.
+    pub fn set_search(&mut self, cx: &mut Context<Self>, query: Option<Query>) {
+        self.selected = None;
+        self.selection.clear();
+        // Changing the search turns a smart folder back into a normal search
+        self.smart_folder = None;
+        self.search = query
+            .filter(|query| !query.is_empty())
+            .map(|query| Search::start(cx, self.current.clone(), query, self.show_hidden));
+
+        if self.search.is_some() {
+            self.entries.clear();
+            self.apply_filter();
+        } else {
+            self.load_content(cx);
+        }
+        cx.notify();
+    }
+
+    /// Run the current search again, eg. so the results reflect a change. Returns `false` if
+    /// there is no search.
+    fn rerun_search(&mut self, cx: &mut Context<Self>) -> bool {
+        let Some(query) = self.search.as_ref().map(|search| search.query.clone()) else {
+            return false;
+        };
+        let smart_folder = self.smart_folder.take();
+        self.set_search(cx, Some(query));
+        self.smart_folder = smart_folder;
+        true
+    }
+
+    /// The smart folder being shown, if any.
+    pub fn smart_folder(&self) -> Option<&SmartFolder> {
+        self.smart_folder.as_ref()
+    }
+
+    /// Go to the smart folder's root and list the results of its search there.
+    pub fn open_smart_folder(&mut self, cx: &mut Context<Self>, smart_folder: SmartFolder) {
+        let query = match Query::parse(&smart_folder.query, smart_folder.mode) {
+            Ok(query) => query,
+            Err(err) => {
+                cx.emit(StateError {
+                    label: format!("Opening the smart folder {}", smart_folder.name),
+                    error: err.message,
+                });
+                return;
+            }
+        };
+
+        if self.current == smart_folder.root {
+            cx.emit(PathChange);
+        } else {
+            self.set_path(cx, smart_folder.root.clone());
+        }
+        self.filter = None;
+        self.set_search(cx, Some(query));
+        self.smart_folder = Some(smart_folder);
+    }
+
+    pub fn add_search_results(&mut self, cx: &mut Context<Self>, nodes: Vec<Node>) {
+        self.entries.extend(nodes.into_iter().map(Rc::new));
+        self.apply_filter();
+        cx.notify();
+    }
+
+    pub fn finish_search(&mut self, cx: &mut Context<Self>) {
+        if let Some(search) = &mut self.search {
+            search.finished = true;
+        }
+        cx.notify();
+    }
+
+    /// Stop the search, keeping the results found so far.
+    pub fn cancel_search(&mut self) {
+        if let Some(search) = &self.search {
+            search.cancel();
+        }
+    }
+
+    /// Where a search result was found, relative to the directory the search started in.
+    pub fn relative_path<'a>(&self, node: &'a Node) -> Option<&'a Path> {
+        let search = self.search.as_ref()?;
+        node.path.strip_prefix(&search.root).ok()
+    }
+
+    /// Go to the directory `path` is in and select it, ending any search.
+    pub fn reveal(&mut self, cx: &mut Context<Self>, path: PathBuf) {
+        let Some(parent) = path.parent() else {
+            return;
+        };
+
+        if self.current == parent {
+            self.search = None;
+            self.smart_folder = None;
+            self.filter = None;
+            self.load_content(cx);
+            cx.emit(PathChange);
+        } else {
+            self.set_path(cx, parent.to_path_buf());
+        }
+        self.select_paths(cx, &[path]);
+    }
+
+    pub fn show_hidden(&self) -> bool {
+        self.show_hidden
+    }
+
+    /// Show or hide hidden files, keeping the selection.
+    pub fn toggle_hidden(&mut self, cx: &mut Context<Self>) {
+        self.show_hidden = !self.show_hidden;
+        let show_hidden = self.show_hidden;
+        Config::update(move |config| config.show_hidden = show_hidden);
+
+        let selected = self
+            .selected_nodes()
+            .iter()
+            .map(|node| node.path.clone())
+            .collect::<Vec<_>>();
+        if !self.rerun_search(cx) {
+            self.load_content(cx);
+        }
+        self.select_paths(cx, &selected);
+    }
+
     pub fn selected(&self) -> Option<usize> {
         self.selected
     }
 
+    pub fn is_selected(&self, ix: usize) -> bool {
+        self.selection.contains(&ix)
+    }
+
+    /// The nodes for every selected row, in listing order.
+    pub fn selected_nodes(&self) -> Vec<Rc<Node>> {
+        self.selection
+            .iter()
+            .filter_map(|ix| self.nodes.get(*ix).cloned())
+            .collect()
+    }
+
     pub fn set_path(&mut self, cx: &mut Context<Self>, path: PathBuf) {
         if self.current != path {
             self.backward.push(self.current.clone());
             self.forward.clear();
             self.current = path;
 
+            let visited = self.current.clone();
+            cx.background_executor()
+                .spawn(async move { frecency::visit(&visited) })
+                .detach();
+
             self.selected = None;
+            self.selection.clear();
+            self.filter = None;
+            self.search = None;
+            self.smart_folder = None;
 
             cx.emit(PathChange);
             cx.notify();
 
-            self.load_content();
+            self.load_content(cx);
         }
     }
 
     pub fn next_selected(&mut self, cx: &mut Context<Self>) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
         if let Some(selected) = self.selected {
             if self.selected != Some(self.nodes.len() - 1) {
-                self.selected = Some(selected + 1);
-                cx.emit(FocusSelection);
-                cx.notify();
+                self.set_selection(cx, selected + 1);
             }
         } else {
-            self.selected = Some(0);
-            cx.emit(FocusSelection);
-            cx.notify();
+            self.set_selection(cx, 0);
         }
     }
 
     pub fn back_selected(&mut self, cx: &mut Context<Self>) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
         if let Some(selected) = self.selected {
             if self.selected != Some(0) {
-                self.selected = Some(selected - 1);
-                cx.emit(FocusSelection);
-                cx.notify();
+                self.set_selection(cx, selected - 1);
             }
         } else {
-            self.selected = Some(0);
-            cx.emit(FocusSelection);
-            cx.notify();
+            self.set_selection(cx, 0);
+        }
+    }
+
+    /// Move the cursor by `delta` rows, stopping at the first and last row.
+    pub fn move_selection(&mut self, cx: &mut Context<Self>, delta: isize) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let ix = match self.selected {
+            Some(selected) => selected.saturating_add_signed(delta),
+            None => 0,
+        };
+        self.set_selection(cx, ix.min(self.nodes.len() - 1));
+    }
+
+    pub fn select_first(&mut self, cx: &mut Context<Self>) {
+        if !self.nodes.is_empty() {
+            self.set_selection(cx, 0);
+        }
+    }
+
+    pub fn select_last(&mut self, cx: &mut Context<Self>) {
+        if !self.nodes.is_empty() {
+            self.set_selection(cx, self.nodes.len() - 1);
         }
     }
 
     pub fn set_selection(&mut self, cx: &mut Context<Self>, selection: usize) {
         self.selected = Some(selection);
+        self.selection = BTreeSet::from([selection]);
+        cx.emit(FocusSelection);
+        cx.notify();
+    }
+
+    /// Add or remove a single row from the selection (cmd/ctrl-click).
+    pub fn toggle_selection(&mut self, cx: &mut Context<Self>, ix: usize) {
+        if !self.selection.remove(&ix) {
+            self.selection.insert(ix);
+            self.selected = Some(ix);
+        } else if self.selected == Some(ix) {
+            self.selected = self.selection.iter().next_back().copied();
+        }
+        cx.notify();
+    }
+
+    /// Select every row between the cursor and `ix` (shift-click).
+    pub fn extend_selection(&mut self, cx: &mut Context<Self>, ix: usize) {
+        let anchor = self.selected.unwrap_or(ix);
+        self.selection = (anchor.min(ix)..=anchor.max(ix)).collect();
+        cx.notify();
+    }
+
+    /// Select the rows for the given paths, if they are in the current listing.
+    pub fn select_paths(&mut self, cx: &mut Context<Self>, paths: &[PathBuf]) {
+        self.selection = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| paths.contains(&node.path))
+            .map(|(ix, _)| ix)
+            .collect();
+        self.selected = self.selection.iter().next().copied();
         cx.emit(FocusSelection);
         cx.notify();
     }
 
     pub fn clear_selection(&mut self, cx: &mut Context<Self>) {
         self.selected = None;
+        self.selection.clear();
         // cx.emit(FocusSelection); // TODO
         cx.notify();
     }
 
-    fn load_content(&mut self) {
+    /// Whether the current directory is inside an archive, which can't be modified.
+    pub fn read_only(&self) -> bool {
+        self.archive.is_some() || self.loading_archive.is_some()
+    }
+
+    /// Whether the archive the current directory is inside of is still being read.
+    pub fn loading(&self) -> bool {
+        self.loading_archive.is_some()
+    }
+
+    /// The current path for the path bar. Directories inside archives end in a `/`, eg. `foo.zip/dir/`.
+    pub fn display_path(&self) -> String {
+        // TODO: Handle non-utf8 paths
+        let mut path = self.current.to_string_lossy().to_string();
+        if self.read_only() && !path.ends_with('/') {
+            path.push('/');
+        }
+        path
+    }
+
+    /// Create a new item in the current directory and select it.
+    /// Returns `None` if the item is hidden and hidden files aren't shown.
+    pub fn create(&mut self, cx: &mut Context<Self>, item: &NewItem) -> io::Result<Option<usize>> {
+        if self.read_only() {
+            return Err(read_only_error());
+        }
+        let path = ops::create(self.path(), item)?;
+        let node = Rc::new(Node::from_path(path)?);
+        self.entries.push(node.clone());
+        if node.hidden && !self.show_hidden {
+            return Ok(None);
+        }
+        // It's shown even if it doesn't match the filter so it can be renamed
+        self.nodes.push(node);
+        self.highlights.push(Vec::new());
+
+        let ix = self.nodes.len() - 1;
+        self.set_selection(cx, ix);
+        Ok(Some(ix))
+    }
+
+    /// Rename the node at `ix` within its current directory.
+    pub fn rename(&mut self, cx: &mut Context<Self>, ix: usize, name: &str) -> io::Result<()> {
+        let Some(node) = self.nodes.get(ix) else {
+            return Ok(());
+        };
+        if node.name == name {
+            return Ok(());
+        }
+        if self.read_only() {
+            return Err(read_only_error());
+        }
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("'{name}' is not a valid name"),
+            ));
+        }
+
+        let to = node.path.with_file_name(name);
+        ops::rename_no_replace(&node.path, &to)?;
+        let renamed = Rc::new(Node::from_path(to)?);
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.path == node.path)
+        {
+            *entry = renamed.clone();
+        }
+        self.nodes[ix] = renamed;
+        self.highlights[ix].clear();
+        cx.notify();
+        Ok(())
+    }
+
+    /// Re-read the current directory, eg. after we have modified it.
+    pub fn reload(&mut self, cx: &mut Context<Self>) {
+        // Search again so the results reflect the change
+        if self.rerun_search(cx) {
+            return;
+        }
+
+        self.selected = None;
+        self.selection.clear();
+        // The archive may have changed too
+        self.archive = None;
+        self.load_content(cx);
+        cx.notify();
+    }
+
+    fn load_content(&mut self, cx: &mut Context<Self>) {
+        if let Some((archive, dir)) = archive::split(&self.current) {
+            let (archive, dir) = (archive.to_path_buf(), dir.to_path_buf());
+            self.load_archive(cx, archive, &dir);
+            self.apply_filter();
+            return;
+        }
+        self.archive = None;
+        self.loading_archive = None;
+
         match std::fs::read_dir(self.path()) {
             Ok(dir) => {
+                let hidden_names = hidden_names(self.path());
+
                 // TODO: Error handing
                 // TODO: Is this running off the main thread?
-                self.nodes = dir
+                self.entries = dir
                     .map(|entry| {
                         let entry = entry.unwrap();
                         let metadata = entry.metadata().unwrap();
+                        let name = entry.file_name();
 
                         Rc::new(Node {
                             path: entry.path(),
-                            name: entry.file_name(),
+                            hidden: is_hidden(&name, &hidden_names),
+                            name,
                             kind: entry.file_type().unwrap().into(),
                             size: metadata.size(),
                             created: metadata.created().unwrap().into(),
                             modified: metadata.modified().unwrap().into(),
                         })
                     })
+                    .filter(|node| self.show_hidden || !node.hidden)
                     .collect();
             }
-            Err(_) => self.nodes = vec![], // TODO: Proper error handling
+            Err(_) => self.entries = vec![], // TODO: Proper error handling
         }
+        self.apply_filter();
+    }
+
+    /// List `dir` within `archive` as if it were a directory.
+    ///
+    /// The archive is read in the background the first time, and the listing is empty until it's done.
+    fn load_archive(&mut self, cx: &mut Context<Self>, archive: PathBuf, dir: &Path) {
+        let entries = match &self.archive {
+            Some((path, entries)) if *path == archive => entries.clone(),
+            _ => {
+                self.archive = None;
+                self.entries.clear();
+                if !matches!(&self.loading_archive, Some((path, _)) if *path == archive) {
+                    let task = cx.spawn({
+                        let archive = archive.clone();
+                        async move |this, cx| {
+                            let list = cx.background_executor().spawn({
+                                let archive = archive.clone();
+                                async move { archive::list(&archive) }
+                            });
+                            let entries = list.await;
+                            this.update(cx, |this, cx| {
+                                // It's listed as empty so the user isn't left waiting
+                                let entries = entries.unwrap_or_else(|err| {
+                                    cx.emit(StateError {
+                                        label: format!("Reading {}", archive.display()),
+                                        error: err.to_string(),
+                                    });
+                                    Vec::new()
+                                });
+                                this.loading_archive = None;
+                                this.archive = Some((archive, Rc::new(entries)));
+                                this.load_content(cx);
+                                cx.notify();
+                            })
+                            .ok();
+                        }
+                    });
+                    self.loading_archive = Some((archive, task));
+                }
+                return;
+            }
+        };
+        self.loading_archive = None;
+
+        // Entries without a time get the archive's
+        let fallback = fs::metadata(&archive)
+            .and_then(|metadata| metadata.modified())
+            .map(DateTime::from)
+            .unwrap_or_else(|_| Local::now());
+        self.entries = archive::children(&entries, dir)
+            .into_iter()
+            .map(|entry| {
+                let name = entry.path.file_name().unwrap_or_default().to_os_string();
+                let modified = entry.modified.unwrap_or(fallback);
+                Rc::new(Node {
+                    path: archive.join(&entry.path),
+                    hidden: name.as_bytes().starts_with(b"."),
+                    name,
+                    kind: if entry.is_dir {
+                        NodeKind::Directory
+                    } else {
+                        NodeKind::File
+                    },
+                    size: entry.size,
+                    created: modified,
+                    modified,
+                })
+            })
+            .filter(|node| self.show_hidden || !node.hidden)
+            .collect();
+        self.archive = Some((archive, entries));
     }
 
     pub fn can_go_back(&self) -> bool {
@@ -159,14 +700,21 @@ impl State {
 
     pub fn go_back(&mut self, cx: &mut Context<Self>) {
         if let Some(previous) = self.backward.pop() {
+            let from = self.current.clone();
             self.forward.push(self.current.clone());
             self.current = previous;
             self.selected = None;
+            self.selection.clear();
+            self.filter = None;
+            self.search = None;
+            self.smart_folder = None;
 
             cx.emit(PathChange);
             cx.notify();
 
-            self.load_content();
+            self.load_content(cx);
+            // Like Finder and Nautilus select the directory we came from, if it's in this one
+            self.select_paths(cx, &[from]);
         }
     }
 
@@ -178,11 +726,16 @@ impl State {
         if let Some(previous) = self.forward.pop() {
             self.backward.push(self.current.clone());
             self.current = previous;
+            self.selected = None;
+            self.selection.clear();
+            self.filter = None;
+            self.search = None;
+            self.smart_folder = None;
 
             cx.emit(PathChange);
             cx.notify();
 
-            self.load_content();
+            self.load_content(cx);
         }
     }
 
@@ -192,7 +745,9 @@ impl State {
 
     pub fn go_up(&mut self, cx: &mut Context<Self>) {
         if let Some(parent) = self.current.parent() {
+            let from = self.current.clone();
             self.set_path(cx, parent.to_path_buf());
+            self.select_paths(cx, &[from]);
         }
     }
 }
@@ -202,3 +757,17 @@ impl EventEmitter<PathChange> for State {}
 
 pub struct FocusSelection;
 impl EventEmitter<FocusSelection> for State {}
+
+/// Something failed which the user should be told about, see [`Jobs::report`](crate::jobs::Jobs::report).
+pub struct StateError {
+    pub label: String,
+    pub error: String,
+}
+impl EventEmitter<StateError> for State {}
+
+fn read_only_error() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::ReadOnlyFilesystem,
+        "files inside archives can't be modified",
+    )
+}
@@ -0,0 +1,357 @@
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use gpui::{App, AppContext, Entity};
+use serde::{Deserialize, Serialize};
+
+use crate::{components::ConflictDialog, jobs::Jobs, ops, state::State};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransferKind {
+    Copy,
+    Move,
+}
+
+impl TransferKind {
+    fn verb(self) -> &'static str {
+        match self {
+            TransferKind::Copy => "Copying",
+            TransferKind::Move => "Moving",
+        }
+    }
+}
+
+/// What to do when the destination of a transfer already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Replace,
+    Skip,
+    /// Transfer it with a " (2)" suffix.
+    KeepBoth,
+    /// Transfer the contents of the source directory into the destination directory.
+    Merge,
+    /// Stop the whole transfer.
+    Cancel,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ConflictAnswer {
+    pub resolution: Resolution,
+    /// Use the same resolution for the rest of the transfer.
+    pub apply_to_all: bool,
+}
+
+/// Copy or move `sources` into the directory `target` as a background job.
+///
+/// If something with the same name already exists the user is asked what to do with `conflicts`.
+pub fn transfer(
+    cx: &mut App,
+    jobs: Entity<Jobs>,
+    state: Entity<State>,
+    conflicts: Entity<ConflictDialog>,
+    kind: TransferKind,
+    sources: Vec<PathBuf>,
+    target: PathBuf,
+) {
+    if sources.is_empty() {
+        return;
+    }
+
+    let (id, cancelled) = jobs.update(cx, |jobs, cx| {
+        jobs.start(
+            cx,
+            format!(
+                "{} {} items to {}",
+                kind.verb(),
+                sources.len(),
+                target.display()
+            ),
+            sources.len(),
+        )
+    });
+
+    cx.spawn(async move |cx| {
+        // Merging directories adds their children to the queue
+        let mut queue = sources
+            .into_iter()
+            .map(|source| (source, target.clone()))
+            .collect::<VecDeque<_>>();
+        let (mut done, mut total) = (0, queue.len());
+        let mut apply_to_all = None::<Resolution>;
+        let mut merged = Vec::new();
+        let mut created = Vec::new();
+
+        while let Some((source, dir)) = queue.pop_front() {
+            if cancelled.load(Ordering::Relaxed) {
+                break;
+            }
+            done += 1;
+
+            let Some(name) = source.file_name() else {
+                continue;
+            };
+            // Moving something into the folder it's already in does nothing
+            if kind == TransferKind::Move && source.parent() == Some(dir.as_path()) {
+                continue;
+            }
+
+            let mut destination = dir.join(name);
+            let mut replace = false;
+            if fs::symlink_metadata(&destination).is_ok() {
+                let both_dirs = is_dir(&source) && is_dir(&destination);
+
+                let resolution = if source == destination {
+                    // Copying something into its own folder makes a duplicate
+                    Resolution::KeepBoth
+                } else if let Some(resolution) =
+                    apply_to_all.filter(|r| *r != Resolution::Merge || both_dirs)
+                {
+                    resolution
+                } else {
+                    let Ok(answer) = conflicts.update(cx, |conflicts, cx| {
+                        conflicts.ask(cx, source.clone(), destination.clone())
+                    }) else {
+                        break;
+                    };
+                    let answer = answer.await.unwrap_or(ConflictAnswer {
+                        resolution: Resolution::Cancel,
+                        apply_to_all: false,
+                    });
+                    if answer.apply_to_all {
+                        apply_to_all = Some(answer.resolution);
+                    }
+                    answer.resolution
+                };
+
+                match resolution {
+                    Resolution::Replace => replace = true,
+                    Resolution::Skip => continue,
+                    Resolution::Merge if both_dirs => {
+                        match fs::read_dir(&source) {
+                            Ok(entries) => {
+                                for entry in entries.flatten() {
+                                    queue.push_back((entry.path(), destination.clone()));
+                                    total += 1;
+                                }
+                                if kind == TransferKind::Move {
+                                    merged.push(source);
+                                }
+                            }
+                            Err(err) => {
+                                jobs.update(cx, |jobs, cx| {
+                                    jobs.error(cx, id, format!("{}: {err}", source.display()))
+                                })
+                                .ok();
+                            }
+                        }
+                        continue;
+                    }
+                    Resolution::KeepBoth | Resolution::Merge => {
+                        destination = ops::unique_path(&dir, &name.to_string_lossy())
+                    }
+                    Resolution::Cancel => {
+                        cancelled.store(true, Ordering::Relaxed);
+                        break;
+                    }
+                }
+            }
+
+            let result = cx
+                .background_executor()
+                .spawn({
+                    let (source, destination, cancelled) =
+                        (source.clone(), destination.clone(), cancelled.clone());
+                    async move {
+                        if replace {
+                            replace_one(kind, &source, &destination, &cancelled)
+                        } else {
+                            transfer_one(kind, &source, &destination, &cancelled)
+                        }
+                    }
+                })
+                .await;
+
+            jobs.update(cx, |jobs, cx| {
+                match result {
+                    Ok(()) if dir == target => created.push(destination),
+                    Ok(()) => {}
+                    Err(err) => jobs.error(cx, id, format!("{}: {err}", source.display())),
+                }
+                jobs.progress(cx, id, done, total);
+            })
+            .ok();
+        }
+
+        // Clean up the directories we moved everything out of while merging.
+        // This fails if anything was skipped which is what we want.
+        for dir in merged.iter().rev() {
+            fs::remove_dir(dir).ok();
+        }
+
+        jobs.update(cx, |jobs, cx| jobs.finish(cx, id)).ok();
+        state
+            .update(cx, |state, cx| {
+                if state.path() == &target {
+                    state.reload(cx);
+                    state.select_paths(cx, &created);
+                } else if kind == TransferKind::Move {
+                    // The sources might have been in the current directory
+                    state.reload(cx);
+                }
+            })
+            .ok();
+    })
+    .detach();
+}
+
+fn is_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path)
+        .map(|m| m.is_dir())
+        .unwrap_or(false)
+}
+
+fn transfer_one(
+    kind: TransferKind,
+    source: &Path,
+    destination: &Path,
+    cancelled: &AtomicBool,
+) -> io::Result<()> {
+    if place(kind, source, destination, cancelled)? {
+        remove_recursive(source)?;
+    }
+    Ok(())
+}
+
+/// Copy or move `source` to `destination`, which mustn't exist. Returns whether `source` still has
+/// to be removed to finish a move, because it was copied across filesystems.
+///
+/// If this fails `source` is left untouched.
+fn place(
+    kind: TransferKind,
+    source: &Path,
+    destination: &Path,
+    cancelled: &AtomicBool,
+) -> io::Result<bool> {
+    if destination.starts_with(source) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "can't copy or move a folder into itself",
+        ));
+    }
+
+    match kind {
+        TransferKind::Copy => copy_recursive(source, destination, cancelled).map(|()| false),
+        TransferKind::Move => match fs::rename(source, destination) {
+            // `rename` doesn't work across filesystems so we fallback to copying
+            Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+                copy_recursive(source, destination, cancelled).map(|()| true)
+            }
+            result => result.map(|()| false),
+        },
+    }
+}
+
+/// Replace `destination` with `source`.
+///
+/// `source` is transferred next to `destination` first and then swapped in, so if anything fails
+/// both are kept.
+fn replace_one(
+    kind: TransferKind,
+    source: &Path,
+    destination: &Path,
+    cancelled: &AtomicBool,
+) -> io::Result<()> {
+    // Replacing a folder with something inside of it would delete the source
+    if source.starts_with(destination) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "can't replace a folder with something inside of it",
+        ));
+    }
+
+    let sibling = |prefix: &str| {
+        destination.with_file_name(format!(
+            ".gpuidrive-{prefix}-{}-{}",
+            std::process::id(),
+            destination
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+        ))
+    };
+    let (new, old) = (sibling("new"), sibling("old"));
+
+    let remove_source = match place(kind, source, &new, cancelled) {
+        Ok(remove_source) => remove_source,
+        Err(err) => {
+            // Only a copy can be left behind, the source is untouched
+            if fs::symlink_metadata(&new).is_ok() {
+                remove_recursive(&new).ok();
+            }
+            return Err(err);
+        }
+    };
+
+    // `rename` can't replace a folder which isn't empty, so the old one is moved aside first
+    let swapped = fs::rename(destination, &old).and_then(|()| {
+        fs::rename(&new, destination).inspect_err(|_| {
+            fs::rename(&old, destination).ok();
+        })
+    });
+    if let Err(err) = swapped {
+        // Undo the transfer
+        if kind == TransferKind::Move && !remove_source {
+            fs::rename(&new, source).ok();
+        } else {
+            remove_recursive(&new).ok();
+        }
+        return Err(err);
+    }
+
+    remove_recursive(&old)?;
+    if remove_source {
+        remove_recursive(source)?;
+    }
+    Ok(())
+}
+
+/// Copy a file, symlink or directory (and everything in it).
+pub fn copy_recursive(source: &Path, destination: &Path, cancelled: &AtomicBool) -> io::Result<()> {
+    if cancelled.load(Ordering::Relaxed) {
+        return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+    }
+
+    let metadata = fs::symlink_metadata(source)?;
+    let file_type = metadata.file_type();
+    if file_type.is_dir() {
+        fs::create_dir(destination)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(
+                &entry.path(),
+                &destination.join(entry.file_name()),
+                cancelled,
+            )?;
+        }
+        fs::set_permissions(destination, metadata.permissions())?;
+    } else if file_type.is_symlink() {
+        std::os::unix::fs::symlink(fs::read_link(source)?, destination)?;
+    } else {
+        // TODO: Report progress for large files
+        fs::copy(source, destination)?;
+    }
+
+    Ok(())
+}
+
+fn remove_recursive(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
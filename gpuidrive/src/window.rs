@@ -1,108 +1,208 @@
+use std::rc::Rc;
+
 use gpui::*;
 
 use crate::{
-    components::{DataTable, PathBar, QuickPreview, open_node},
-    state::State,
+    actions::{
+        ClearSelection, CloseWindow, Compress, FindFile, GoBack, GoForward, GoUp, JumpToFolder,
+        OpenFilter, OpenSelected, OpenSelectedExternally, Rename, SearchContents, SearchSubfolders,
+        SelectFirst, SelectLast, SelectNext, SelectPrevious, ToggleCommandPalette,
+        ToggleHiddenFiles, ToggleProperties, ToggleQuickPreview,
+    },
+    components::{
+        BatchRename, CommandPalette, CompressDialog, ConflictDialog, ContentSearch, DataTable,
+        FileFinder, FilterBar, JumpList, PathBar, Picker, PropertiesPanel, QuickPreview,
+        SaveSearch, ShowProperties, Sidebar, StatusBar, open_node,
+    },
+    jobs::Jobs,
+    state::{Node, State, StateError},
 };
 
-actions!(example, [CloseWindow]);
-
 pub struct MainWindow {
     state: Entity<State>,
     path_bar: Entity<PathBar>,
+    filter_bar: Entity<FilterBar>,
+    sidebar: Entity<Sidebar>,
     data_table: Entity<DataTable>,
     quick_preview: Entity<QuickPreview>,
-    focus: FocusHandle,
+    batch_rename: Entity<BatchRename>,
+    compress_dialog: Entity<CompressDialog>,
+    status_bar: Entity<StatusBar>,
+    properties_panel: Entity<PropertiesPanel>,
+    content_search: Entity<ContentSearch>,
+    file_finder: Entity<Picker<FileFinder>>,
+    jump_list: Entity<Picker<JumpList>>,
+    command_palette: Entity<Picker<CommandPalette>>,
+    conflict_dialog: Entity<ConflictDialog>,
 }
 
 impl MainWindow {
     pub fn init(cx: &mut Context<Self>, window: &mut Window) -> Self {
-        let focus = cx.focus_handle();
-        focus.focus(window);
+        let state = cx.new(State::init);
+        let jobs = cx.new(|_| Jobs::default());
+        cx.subscribe(&state, {
+            let jobs = jobs.clone();
+            move |_, _, StateError { label, error }: &StateError, cx| {
+                jobs.update(cx, |jobs, cx| jobs.report(cx, label.clone(), error.clone()));
+            }
+        })
+        .detach();
+        let conflict_dialog = cx.new(|_| ConflictDialog::init());
+        let data_table =
+            cx.new(|cx| DataTable::new(cx, state.clone(), jobs.clone(), conflict_dialog.clone()));
+        let properties_panel = cx.new(|cx| PropertiesPanel::init(cx, state.clone()));
+        cx.subscribe(&data_table, {
+            let properties_panel = properties_panel.clone();
+            move |_, _, _: &ShowProperties, cx| {
+                properties_panel.update(cx, |panel, cx| panel.open(cx));
+            }
+        })
+        .detach();
+        data_table.read(cx).focus_handle.focus(window);
+        let filter_bar = cx.new(|cx| FilterBar::init(cx, state.clone()));
+        let sidebar = cx.new(|_| Sidebar::init(state.clone(), data_table.clone()));
+        cx.subscribe(&filter_bar, {
+            let sidebar = sidebar.clone();
+            move |_, _, SaveSearch(smart_folder): &SaveSearch, cx| {
+                sidebar.update(cx, |sidebar, cx| sidebar.add(smart_folder.clone(), cx));
+            }
+        })
+        .detach();
+        let quick_preview = cx.new(|cx| {
+            cx.observe(&state, |quick_preview: &mut QuickPreview, state, cx| {
+                let state = state.read(cx);
+                quick_preview.set(state.selected().and_then(|s| state.nodes().get(s)).cloned());
+            })
+            .detach();
 
-        let state = cx.new(|_| State::init());
-        Self {
-            path_bar: cx.new(|cx| PathBar::init(cx, state.clone())),
-            data_table: cx.new(|cx| DataTable::new(state.clone())),
-            quick_preview: cx.new(|cx| {
-                cx.observe(&state, |quick_preview: &mut QuickPreview, state, cx| {
-                    let state = state.read(cx);
-                    quick_preview.set(state.selected().and_then(|s| state.nodes().get(s)).cloned());
-                })
-                .detach();
+            QuickPreview::init(cx)
+        });
 
-                QuickPreview::init()
-            }),
+        Self {
+            path_bar: cx.new(|cx| PathBar::init(cx, state.clone(), data_table.clone())),
+            filter_bar,
+            sidebar,
+            data_table,
+            content_search: cx
+                .new(|cx| ContentSearch::init(cx, state.clone(), quick_preview.clone())),
+            quick_preview,
+            batch_rename: cx.new(|cx| BatchRename::init(cx, state.clone())),
+            compress_dialog: cx.new(|cx| CompressDialog::init(cx, state.clone(), jobs.clone())),
+            file_finder: cx.new(|cx| Picker::new(cx, FileFinder::new(state.clone(), jobs.clone()))),
+            jump_list: cx.new(|cx| Picker::new(cx, JumpList::new(state.clone(), jobs.clone()))),
+            command_palette: cx.new(|cx| Picker::new(cx, CommandPalette::default())),
+            status_bar: cx.new(|_| StatusBar::init(jobs.clone())),
+            properties_panel,
+            conflict_dialog,
             state,
-            focus,
         }
     }
 }
 
-impl Render for MainWindow {
-    fn render(&mut self, _: &mut Window, _: &mut Context<Self>) -> impl IntoElement {
-        div()
-            // TODO: move this onto the data view
-            .on_key_down({
-                let state = self.state.clone();
-                let preview = self.quick_preview.clone();
+impl MainWindow {
+    /// An action handler which only needs to update the state.
+    fn update_state<A: Action>(
+        &self,
+        f: fn(&mut State, &mut Context<State>),
+    ) -> impl Fn(&A, &mut Window, &mut App) + 'static {
+        let state = self.state.clone();
+        move |_, _, cx| state.update(cx, f)
+    }
+
+    fn selected_node(&self, cx: &App) -> Option<Rc<Node>> {
+        let state = self.state.read(cx);
+        state.selected().and_then(|s| state.nodes().get(s)).cloned()
+    }
 
-                move |event, _, cx| {
-                    let modifier =
-                        event.keystroke.modifiers.platform || event.keystroke.modifiers.shift; // TODO: Make this better
+    fn open_selected(&mut self, _: &OpenSelected, _: &mut Window, cx: &mut Context<Self>) {
+        if let Some(node) = self.selected_node(cx) {
+            open_node(&self.state, cx, &node, false);
+        }
+    }
 
-                    match &*event.keystroke.key {
-                        "[" if modifier => {
-                            state.update(cx, |state, cx| state.go_back(cx));
-                        }
-                        "]" if modifier => {
-                            state.update(cx, |state, cx| state.go_forward(cx));
-                        }
-                        "up" if modifier => {
-                            state.update(cx, |state, cx| state.go_up(cx));
-                        }
-                        "down" if modifier => {
-                            let s = state.read(cx);
-                            if let Some(selection) = s.selected() {
-                                let node = s.nodes().get(selection).unwrap().clone();
+    fn open_selected_externally(
+        &mut self,
+        _: &OpenSelectedExternally,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(node) = self.selected_node(cx) {
+            open_node(&self.state, cx, &node, true);
+        }
+    }
 
-                                open_node(&state, cx, &node, false);
-                            }
-                        }
-                        // TODO: How to navigate forward in history stack with keyboard????
-                        "o" if modifier => {
-                            let s = state.read(cx);
-                            if let Some(selection) = s.selected() {
-                                let node = s.nodes().get(selection).unwrap().clone();
+    /// Batch rename when multiple nodes are selected, otherwise rename inline.
+    fn rename(&mut self, _: &Rename, window: &mut Window, cx: &mut Context<Self>) {
+        if self.state.read(cx).selected_nodes().len() > 1 {
+            self.batch_rename
+                .update(cx, |batch_rename, cx| batch_rename.open(window, cx));
+        } else {
+            self.data_table
+                .update(cx, |data_table, cx| data_table.rename_selected(window, cx));
+        }
+    }
+}
 
-                                open_node(&state, cx, &node, true);
-                            }
-                        }
-                        "up" => {
-                            state.update(cx, |state, cx| state.back_selected(cx));
-                        }
-                        "down" => {
-                            state.update(cx, |state, cx| state.next_selected(cx));
-                        }
-                        "escape" => {
-                            state.update(cx, |state, cx| state.clear_selection(cx));
-                        }
-                        "enter" => {
-                            // TODO: Implement rename
-                        }
-                        "space" => {
-                            preview.update(cx, |s, cx| {
-                                s.toggle();
-                                cx.notify();
-                            });
-                        }
-                        _ => {}
-                    }
-                }
-            })
+impl Render for MainWindow {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .on_action::<GoBack>(self.update_state(|state, cx| state.go_back(cx)))
+            .on_action::<GoForward>(self.update_state(|state, cx| state.go_forward(cx)))
+            .on_action::<GoUp>(self.update_state(|state, cx| state.go_up(cx)))
+            .on_action::<SelectPrevious>(self.update_state(|state, cx| state.back_selected(cx)))
+            .on_action::<SelectNext>(self.update_state(|state, cx| state.next_selected(cx)))
+            .on_action::<SelectFirst>(self.update_state(|state, cx| state.select_first(cx)))
+            .on_action::<SelectLast>(self.update_state(|state, cx| state.select_last(cx)))
+            .on_action::<ClearSelection>(self.update_state(|state, cx| state.clear_selection(cx)))
+            .on_action::<ToggleHiddenFiles>(self.update_state(|state, cx| state.toggle_hidden(cx)))
+            .on_action(cx.listener(Self::open_selected))
+            .on_action(cx.listener(Self::open_selected_externally))
+            .on_action(cx.listener(Self::rename))
+            .on_action(cx.listener(|this, _: &ToggleQuickPreview, window, cx| {
+                this.quick_preview
+                    .update(cx, |quick_preview, cx| quick_preview.toggle(window, cx))
+            }))
+            .on_action(cx.listener(|this, _: &ToggleProperties, _, cx| {
+                this.properties_panel
+                    .update(cx, |panel, cx| panel.toggle(cx))
+            }))
+            .on_action(cx.listener(|this, _: &OpenFilter, window, cx| {
+                // Whatever was just typed to jump to a row becomes the start of the filter
+                let text = this
+                    .data_table
+                    .update(cx, |data_table, _| data_table.take_type_ahead());
+                this.filter_bar.update(cx, |filter_bar, cx| {
+                    filter_bar.open(false, text.as_deref(), window, cx)
+                })
+            }))
+            .on_action(cx.listener(|this, _: &SearchContents, window, cx| {
+                this.content_search
+                    .update(cx, |content_search, cx| content_search.toggle(window, cx))
+            }))
+            .on_action(cx.listener(|this, _: &SearchSubfolders, window, cx| {
+                this.filter_bar
+                    .update(cx, |filter_bar, cx| filter_bar.open(true, None, window, cx))
+            }))
+            .on_action(cx.listener(|this, _: &Compress, window, cx| {
+                this.compress_dialog
+                    .update(cx, |compress_dialog, cx| compress_dialog.open(window, cx))
+            }))
+            .on_action(cx.listener(|this, _: &FindFile, window, cx| {
+                this.file_finder
+                    .update(cx, |file_finder, cx| file_finder.toggle(window, cx))
+            }))
+            .on_action(cx.listener(|this, _: &JumpToFolder, window, cx| {
+                this.jump_list
+                    .update(cx, |jump_list, cx| jump_list.toggle(window, cx))
+            }))
+            .on_action(cx.listener(|this, _: &ToggleCommandPalette, window, cx| {
+                this.command_palette.update(cx, |command_palette, cx| {
+                    command_palette.delegate.collect_commands(window, cx);
+                    command_palette.toggle(window, cx)
+                })
+            }))
             .font_family(".SystemUIFont")
             .on_action(|_: &CloseWindow, window, _| window.remove_window())
-            .track_focus(&self.focus)
             .relative() // Makes this the positioning context for absolute children
             .size_full() // Or whatever size you need
             .child(
@@ -115,10 +215,26 @@ impl Render for MainWindow {
                             .flex_col()
                             .size_full()
                             .child(self.path_bar.clone())
-                            .child(self.data_table.clone())
-                            .child(self.quick_preview.clone()),
+                            .child(self.filter_bar.clone())
+                            .child(
+                                div()
+                                    .flex()
+                                    .flex_row()
+                                    .size_full()
+                                    .child(self.sidebar.clone())
+                                    .child(self.data_table.clone())
+                                    .child(self.properties_panel.clone())
+                                    .child(self.content_search.clone()),
+                            )
+                            .child(self.status_bar.clone()),
                     ),
             )
             .child(self.quick_preview.clone())
+            .child(self.batch_rename.clone())
+            .child(self.compress_dialog.clone())
+            .child(self.file_finder.clone())
+            .child(self.jump_list.clone())
+            .child(self.command_palette.clone())
+            .child(self.conflict_dialog.clone())
     }
 }
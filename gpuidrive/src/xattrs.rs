@@ -0,0 +1,125 @@
+//! Extended attributes (`user.*`, `security.selinux`, `system.posix_acl_access`, etc).
+
+use std::{io, path::Path};
+
+use crate::metadata::Users;
+
+/// Only `user.*` attributes can be changed by regular users, the other namespaces are managed by the system.
+pub const EDITABLE_PREFIX: &str = "user.";
+
+const ACL_ACCESS: &str = "system.posix_acl_access";
+const ACL_DEFAULT: &str = "system.posix_acl_default";
+
+#[derive(Debug, Clone)]
+pub struct Xattr {
+    pub name: String,
+    pub value: Vec<u8>,
+}
+
+impl Xattr {
+    pub fn is_editable(&self) -> bool {
+        self.name.starts_with(EDITABLE_PREFIX)
+    }
+
+    /// The value as text, if it's printable UTF-8.
+    pub fn text(&self) -> Option<&str> {
+        // Values written by C programs are often NUL terminated
+        let value = self.value.strip_suffix(&[0]).unwrap_or(&self.value);
+        std::str::from_utf8(value).ok().filter(|text| {
+            !text
+                .chars()
+                .any(|c| c.is_control() && c != '\n' && c != '\t')
+        })
+    }
+
+    pub fn hex(&self) -> String {
+        self.value
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// A human readable version of POSIX ACLs, like `getfacl`.
+    pub fn acl(&self, users: &Users) -> Option<Vec<String>> {
+        match self.name.as_str() {
+            ACL_ACCESS => parse_acl(&self.value, users),
+            ACL_DEFAULT => Some(
+                parse_acl(&self.value, users)?
+                    .into_iter()
+                    .map(|entry| format!("default:{entry}"))
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// List the extended attributes of `path`. This doesn't follow symbolic links.
+pub fn list(path: &Path) -> io::Result<Vec<Xattr>> {
+    let mut xattrs = xattr::list(path)?
+        .map(|name| {
+            Ok(Xattr {
+                value: xattr::get(path, &name)?.unwrap_or_default(),
+                name: name.to_string_lossy().to_string(),
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    xattrs.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(xattrs)
+}
+
+fn check_editable(name: &str) -> io::Result<()> {
+    if !name.starts_with(EDITABLE_PREFIX) || name.len() == EDITABLE_PREFIX.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Only {EDITABLE_PREFIX}* attributes can be changed"),
+        ));
+    }
+    Ok(())
+}
+
+pub fn set(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+    check_editable(name)?;
+    xattr::set(path, name, value)
+}
+
+pub fn remove(path: &Path, name: &str) -> io::Result<()> {
+    check_editable(name)?;
+    xattr::remove(path, name)
+}
+
+/// Parse the `posix_acl_xattr` format from the Linux kernel.
+///
+/// It's a little endian `u32` version followed by `(u16 tag, u16 permissions, u32 id)` entries.
+fn parse_acl(value: &[u8], users: &Users) -> Option<Vec<String>> {
+    let (version, entries) = value.split_first_chunk::<4>()?;
+    if u32::from_le_bytes(*version) != 2 || entries.len() % 8 != 0 {
+        return None;
+    }
+
+    entries
+        .chunks_exact(8)
+        .map(|entry| {
+            let tag = u16::from_le_bytes([entry[0], entry[1]]);
+            let perm = u16::from_le_bytes([entry[2], entry[3]]);
+            let id = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]);
+
+            let qualifier = match tag {
+                0x01 => "user:".to_string(),
+                0x02 => format!("user:{}", users.user_name(id)),
+                0x04 => "group:".to_string(),
+                0x08 => format!("group:{}", users.group_name(id)),
+                0x10 => "mask:".to_string(),
+                0x20 => "other:".to_string(),
+                _ => return None,
+            };
+            let perms = [(0o4, 'r'), (0o2, 'w'), (0o1, 'x')]
+                .map(|(bit, c)| if perm & bit != 0 { c } else { '-' })
+                .iter()
+                .collect::<String>();
+
+            Some(format!("{qualifier}:{perms}"))
+        })
+        .collect()
+}